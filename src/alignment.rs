@@ -0,0 +1,203 @@
+//! Aligning molecules and conformers and computing RMSDs between them.
+
+use std::ffi::{c_int, c_uint};
+
+use crate::{RDError, ROMol};
+
+/// The result of aligning a probe molecule onto a reference.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Alignment {
+    /// the root-mean-square deviation between the aligned atoms in Å
+    pub rmsd: f64,
+    /// the 4x4 row-major transformation matrix that was applied to the probe
+    pub transform: [[f64; 4]; 4],
+}
+
+fn flatten_map(atom_map: Option<&[(usize, usize)]>) -> Vec<c_uint> {
+    atom_map
+        .unwrap_or_default()
+        .iter()
+        .flat_map(|&(p, r)| [p as c_uint, r as c_uint])
+        .collect()
+}
+
+/// Build an [Alignment] from the outputs of the C wrapper, which returns
+/// `false` after printing RDKit's exception to stderr if the alignment fails,
+/// for example because of an invalid atom map.
+fn alignment(
+    ok: bool,
+    rmsd: f64,
+    transform: [f64; 16],
+) -> Result<Alignment, RDError> {
+    if !ok {
+        return Err(RDError);
+    }
+    let mut ret = [[0.0; 4]; 4];
+    for (row, chunk) in ret.iter_mut().zip(transform.chunks(4)) {
+        row.copy_from_slice(chunk);
+    }
+    Ok(Alignment { rmsd, transform: ret })
+}
+
+/// Align conformer `probe_conf` of `probe` onto conformer `ref_conf` of
+/// `reference`, moving the probe's coordinates. `atom_map` is a sequence of
+/// (probe atom, reference atom) pairs to align. If it is `None`, the
+/// molecules are expected to have the same atom order, and every atom is used.
+///
+/// This does not consider symmetry, see [get_best_rms] for that.
+pub fn align_mol(
+    probe: &mut ROMol,
+    reference: &ROMol,
+    probe_conf: usize,
+    ref_conf: usize,
+    atom_map: Option<&[(usize, usize)]>,
+) -> Result<Alignment, RDError> {
+    let map = flatten_map(atom_map);
+    let mut transform = [0.0; 16];
+    let mut rmsd = 0.0;
+    let ok = unsafe {
+        rdkit_sys::RDKit_AlignMol(
+            probe.0,
+            reference.0,
+            probe_conf as c_int,
+            ref_conf as c_int,
+            map.as_ptr(),
+            map.len(),
+            transform.as_mut_ptr(),
+            &mut rmsd,
+        )
+    };
+    alignment(ok, rmsd, transform)
+}
+
+/// Like [align_mol], but tries every symmetry-equivalent mapping of atoms
+/// between `probe` and `reference` and keeps the one with the lowest RMSD.
+/// The probe is left in the best alignment. This can be very slow for
+/// molecules with many equivalent atoms, such as those with explicit
+/// hydrogens on methyl groups.
+pub fn get_best_rms(
+    probe: &mut ROMol,
+    reference: &ROMol,
+    probe_conf: usize,
+    ref_conf: usize,
+) -> Result<Alignment, RDError> {
+    let mut transform = [0.0; 16];
+    let mut rmsd = 0.0;
+    let ok = unsafe {
+        rdkit_sys::RDKit_GetBestRMS(
+            probe.0,
+            reference.0,
+            probe_conf as c_int,
+            ref_conf as c_int,
+            transform.as_mut_ptr(),
+            &mut rmsd,
+        )
+    };
+    alignment(ok, rmsd, transform)
+}
+
+impl ROMol {
+    /// Align every conformer of `self` onto the first one, using only
+    /// `atom_ids` if provided. Returns the RMSD of each of the remaining
+    /// conformers to the first.
+    pub fn align_mol_conformers(
+        &mut self,
+        atom_ids: Option<&[usize]>,
+    ) -> Vec<f64> {
+        let ids: Vec<_> = atom_ids
+            .unwrap_or_default()
+            .iter()
+            .map(|&i| i as c_uint)
+            .collect();
+        unsafe {
+            let mut len = 0;
+            let rmsds = rdkit_sys::RDKit_AlignMolConformers(
+                self.0,
+                ids.as_ptr(),
+                ids.len(),
+                &mut len,
+            );
+            Vec::from_raw_parts(rmsds, len, len)
+        }
+    }
+
+    /// The RMSD between conformers `conf1` and `conf2` of `self`, using only
+    /// `atom_ids` if provided. Unless `prealigned` is true, `conf2` is first
+    /// aligned onto `conf1`, moving its coordinates.
+    pub fn get_conformer_rms(
+        &mut self,
+        conf1: usize,
+        conf2: usize,
+        atom_ids: Option<&[usize]>,
+        prealigned: bool,
+    ) -> f64 {
+        let ids: Vec<_> = atom_ids
+            .unwrap_or_default()
+            .iter()
+            .map(|&i| i as c_uint)
+            .collect();
+        unsafe {
+            rdkit_sys::RDKit_GetConformerRMS(
+                self.0,
+                conf1 as c_int,
+                conf2 as c_int,
+                ids.as_ptr(),
+                ids.len(),
+                prealigned,
+            )
+        }
+    }
+
+    /// Compute the symmetric matrix of pairwise RMSDs between all of the
+    /// conformers of `self`, in the order of [ROMol::conformer_ids]. See
+    /// [ROMol::get_conformer_rms] for the meaning of the arguments.
+    pub fn conformer_rms_matrix(
+        &mut self,
+        atom_ids: Option<&[usize]>,
+        prealigned: bool,
+    ) -> Vec<Vec<f64>> {
+        let ids = self.conformer_ids();
+        let n = ids.len();
+        let mut ret = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            for j in 0..i {
+                let rms = self
+                    .get_conformer_rms(ids[j], ids[i], atom_ids, prealigned);
+                ret[i][j] = rms;
+                ret[j][i] = rms;
+            }
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::embedded;
+
+    #[test]
+    fn align_to_self() {
+        let reference = embedded("CCOC(=O)C", 1);
+        let mut probe = reference.clone();
+        let got = align_mol(&mut probe, &reference, 0, 0, None).unwrap();
+        assert!(got.rmsd < 1e-6);
+        let got = get_best_rms(&mut probe, &reference, 0, 0).unwrap();
+        assert!(got.rmsd < 1e-6);
+    }
+
+    #[test]
+    fn rms_matrix() {
+        let mut mol = embedded("CCCCO", 4);
+        let rmsds = mol.align_mol_conformers(None);
+        assert_eq!(rmsds.len(), 3);
+        let mat = mol.conformer_rms_matrix(None, true);
+        assert_eq!(mat.len(), 4);
+        for (i, row) in mat.iter().enumerate() {
+            assert_eq!(row[i], 0.0);
+            for (j, rms) in row.iter().enumerate() {
+                assert_eq!(*rms, mat[j][i]);
+            }
+        }
+    }
+}
@@ -1,18 +1,44 @@
-use std::ops::Index;
+use std::ops::{BitAnd, BitOr, BitXor, Index, Not};
 
+use crate::RDError;
+
+const BASE64: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// A fixed-length vector of bits, stored in 64-bit words with bit 0 in the
+/// lowest bit of the first word. Any bits in the last word past the end of
+/// the vector are always zero.
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct BitVector {
     data: Vec<u64>,
+    /// the number of bits in the vector, which may not fill the last word
+    nbits: usize,
 }
 
 impl BitVector {
     pub fn new() -> Self {
-        Self { data: Vec::new() }
+        Self { data: Vec::new(), nbits: 0 }
+    }
+
+    /// A vector of `nbits` bits, all unset.
+    pub fn zeros(nbits: usize) -> Self {
+        Self { data: vec![0; nbits.div_ceil(64)], nbits }
+    }
+
+    /// Build a vector of `nbits` bits from its little-endian 64-bit words.
+    /// Panics if `data` does not have the right number of words for `nbits`.
+    pub(crate) fn from_words(data: Vec<u64>, nbits: usize) -> Self {
+        assert_eq!(data.len(), nbits.div_ceil(64));
+        Self { data, nbits }
     }
 
+    /// Iterate over the 64-bit words of the vector.
     pub fn iter(&self) -> impl Iterator<Item = &u64> {
         self.data.iter()
     }
 
+    /// The number of 64-bit words in the vector. See [BitVector::num_bits]
+    /// for the number of bits.
     pub fn len(&self) -> usize {
         self.data.len()
     }
@@ -22,11 +48,208 @@ impl BitVector {
         self.len() == 0
     }
 
+    /// The number of bits in the vector, as opposed to the number of words
+    /// returned by [BitVector::len].
+    pub fn num_bits(&self) -> usize {
+        self.nbits
+    }
+
+    /// The number of set bits.
     pub fn count(&self) -> usize {
         self.data
             .iter()
             .fold(0, |acc, n| acc + n.count_ones() as usize)
     }
+
+    fn check_bit(&self, bit: usize) {
+        assert!(
+            bit < self.nbits,
+            "bit {bit} out of range for BitVector of {} bits",
+            self.nbits
+        );
+    }
+
+    /// Reports whether `bit` is set. Panics if `bit` is out of range.
+    pub fn get(&self, bit: usize) -> bool {
+        self.check_bit(bit);
+        self.data[bit / 64] >> (bit % 64) & 1 == 1
+    }
+
+    /// Set `bit` to 1. Panics if `bit` is out of range.
+    pub fn set(&mut self, bit: usize) {
+        self.check_bit(bit);
+        self.data[bit / 64] |= 1 << (bit % 64);
+    }
+
+    /// Set `bit` to 0. Panics if `bit` is out of range.
+    pub fn clear(&mut self, bit: usize) {
+        self.check_bit(bit);
+        self.data[bit / 64] &= !(1 << (bit % 64));
+    }
+
+    /// Iterate over the indices of the set bits in increasing order.
+    pub fn on_bits(&self) -> impl Iterator<Item = usize> + '_ {
+        self.data.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(64 * i + bit)
+            })
+        })
+    }
+
+    /// Fold the vector by `factor`, like RDKit's `FoldFingerprint`: the
+    /// result has `num_bits / factor` bits, and bit `i` of `self` is ORed
+    /// into bit `i % (num_bits / factor)`. Panics if `factor` is zero or does
+    /// not evenly divide the number of bits.
+    pub fn fold(&self, factor: usize) -> Self {
+        assert!(
+            self.nbits.checked_rem(factor) == Some(0),
+            "cannot fold {} bits by a factor of {factor}",
+            self.nbits
+        );
+        let mut ret = Self::zeros(self.nbits / factor);
+        for bit in self.on_bits() {
+            ret.set(bit % ret.nbits);
+        }
+        ret
+    }
+
+    /// Zero any bits in the last word past the end of the vector.
+    fn mask_tail(&mut self) {
+        let rem = self.nbits % 64;
+        if let (true, Some(last)) = (rem > 0, self.data.last_mut()) {
+            *last &= (1 << rem) - 1;
+        }
+    }
+
+    /// The vector as little-endian bytes, with bit 0 in the lowest bit of the
+    /// first byte. The length is the number of bits rounded up to a multiple
+    /// of 8.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.data
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .take(self.nbits.div_ceil(8))
+            .collect()
+    }
+
+    /// The inverse of [BitVector::to_bytes]. Returns an error if `bytes`
+    /// does not have the right length for `nbits` or sets bits past the end
+    /// of the vector.
+    pub fn from_bytes(bytes: &[u8], nbits: usize) -> Result<Self, RDError> {
+        if bytes.len() != nbits.div_ceil(8) {
+            eprintln!(
+                "expected {} bytes for {nbits} bits, found {}",
+                nbits.div_ceil(8),
+                bytes.len()
+            );
+            return Err(RDError);
+        }
+        let mut ret = Self::zeros(nbits);
+        for (i, &byte) in bytes.iter().enumerate() {
+            ret.data[i / 8] |= (byte as u64) << (8 * (i % 8));
+        }
+        let words = ret.data.clone();
+        ret.mask_tail();
+        if ret.data != words {
+            eprintln!("bytes set bits past the end of {nbits} bits");
+            return Err(RDError);
+        }
+        Ok(ret)
+    }
+
+    /// The bytes of [BitVector::to_bytes] as lowercase hex, as used in the
+    /// FPS format (see [crate::fingerprint::fps]). Bit 0 is the lowest bit of
+    /// the first byte.
+    pub fn to_hex(&self) -> String {
+        self.to_bytes().iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// The inverse of [BitVector::to_hex], accepting upper or lowercase hex.
+    pub fn from_hex(hex: &str, nbits: usize) -> Result<Self, RDError> {
+        let err = || {
+            eprintln!("invalid hex string: {hex}");
+            RDError
+        };
+        let digit = |c: u8| (c as char).to_digit(16).ok_or_else(err);
+        let pairs = hex.as_bytes().chunks_exact(2);
+        if !pairs.remainder().is_empty() {
+            return Err(err());
+        }
+        let bytes = pairs
+            .map(|pair| Ok((digit(pair[0])? << 4 | digit(pair[1])?) as u8))
+            .collect::<Result<Vec<_>, RDError>>()?;
+        Self::from_bytes(&bytes, nbits)
+    }
+
+    /// The bytes of [BitVector::to_bytes] in standard, padded base64.
+    pub fn to_base64(&self) -> String {
+        let bytes = self.to_bytes();
+        let mut ret = String::with_capacity(bytes.len().div_ceil(3) * 4);
+        for chunk in bytes.chunks(3) {
+            let n = chunk
+                .iter()
+                .enumerate()
+                .fold(0u32, |acc, (i, &b)| acc | (b as u32) << (16 - 8 * i));
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    ret.push(
+                        BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char,
+                    );
+                } else {
+                    ret.push('=');
+                }
+            }
+        }
+        ret
+    }
+
+    /// The inverse of [BitVector::to_base64].
+    pub fn from_base64(s: &str, nbits: usize) -> Result<Self, RDError> {
+        let err = || {
+            eprintln!("invalid base64 string: {s}");
+            RDError
+        };
+        let chunks = s.as_bytes().chunks_exact(4);
+        if !chunks.remainder().is_empty() {
+            return Err(err());
+        }
+        let last = s.len() / 4;
+        let mut bytes = Vec::with_capacity(last * 3);
+        for (i, chunk) in chunks.enumerate() {
+            // padding is only allowed at the very end
+            let pad = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+            if pad > 2 || (pad > 0 && i + 1 != last) {
+                return Err(err());
+            }
+            let mut n = 0u32;
+            for (i, &c) in chunk[..4 - pad].iter().enumerate() {
+                let v = BASE64.iter().position(|&b| b == c).ok_or_else(err)?;
+                n |= (v as u32) << (18 - 6 * i);
+            }
+            bytes.extend(n.to_be_bytes()[1..4 - pad].iter());
+        }
+        Self::from_bytes(&bytes, nbits)
+    }
+
+    fn zip_with(&self, other: &Self, f: impl Fn(u64, u64) -> u64) -> Self {
+        assert_eq!(
+            self.nbits, other.nbits,
+            "BitVectors must have the same number of bits"
+        );
+        let data = self
+            .data
+            .iter()
+            .zip(&other.data)
+            .map(|(&a, &b)| f(a, b))
+            .collect();
+        Self { data, nbits: self.nbits }
+    }
 }
 
 impl Default for BitVector {
@@ -44,6 +267,7 @@ impl From<&[bool]> for BitVector {
             }
             bv.data[i / 64] |= (*v as u64) << (i % 64);
         }
+        bv.nbits = value.len();
         bv
     }
 }
@@ -56,6 +280,46 @@ impl Index<usize> for BitVector {
     }
 }
 
+impl BitAnd for &BitVector {
+    type Output = BitVector;
+
+    /// Panics if the vectors have different numbers of bits.
+    fn bitand(self, rhs: Self) -> Self::Output {
+        self.zip_with(rhs, |a, b| a & b)
+    }
+}
+
+impl BitOr for &BitVector {
+    type Output = BitVector;
+
+    /// Panics if the vectors have different numbers of bits.
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.zip_with(rhs, |a, b| a | b)
+    }
+}
+
+impl BitXor for &BitVector {
+    type Output = BitVector;
+
+    /// Panics if the vectors have different numbers of bits.
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        self.zip_with(rhs, |a, b| a ^ b)
+    }
+}
+
+impl Not for &BitVector {
+    type Output = BitVector;
+
+    fn not(self) -> Self::Output {
+        let mut ret = BitVector {
+            data: self.data.iter().map(|w| !w).collect(),
+            nbits: self.nbits,
+        };
+        ret.mask_tail();
+        ret
+    }
+}
+
 impl std::fmt::Debug for BitVector {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for row in &self.data {
@@ -64,3 +328,73 @@ impl std::fmt::Debug for BitVector {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::bv;
+
+    #[test]
+    fn bits() {
+        let mut v = BitVector::zeros(70);
+        assert_eq!(v.num_bits(), 70);
+        assert_eq!(v.len(), 2);
+        v.set(3);
+        v.set(69);
+        assert!(v.get(3) && v.get(69) && !v.get(4));
+        v.clear(3);
+        assert!(!v.get(3));
+        assert_eq!(v.on_bits().collect::<Vec<_>>(), [69]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_range() {
+        BitVector::zeros(70).get(70);
+    }
+
+    #[test]
+    fn ops() {
+        let a = bv(&[0, 1, 65], 70);
+        let b = bv(&[1, 2, 65], 70);
+        assert_eq!(&a & &b, bv(&[1, 65], 70));
+        assert_eq!(&a | &b, bv(&[0, 1, 2, 65], 70));
+        assert_eq!(&a ^ &b, bv(&[0, 2], 70));
+        let not = !&a;
+        assert_eq!(not.count(), 67);
+        assert_eq!(!&not, a);
+    }
+
+    #[test]
+    fn fold() {
+        let v = bv(&[1, 9, 10], 16);
+        assert_eq!(v.fold(2), bv(&[1, 2], 8));
+        assert_eq!(v.fold(1), v);
+    }
+
+    #[test]
+    fn encodings() {
+        let v = bv(&[0, 9, 71], 72);
+        assert_eq!(v.to_bytes(), [1, 2, 0, 0, 0, 0, 0, 0, 0x80]);
+        assert_eq!(v.to_hex(), "010200000000000080");
+        assert_eq!(BitVector::from_hex("010200000000000080", 72).unwrap(), v);
+        assert_eq!(v.to_base64(), "AQIAAAAAAACA");
+        assert_eq!(BitVector::from_base64("AQIAAAAAAACA", 72).unwrap(), v);
+
+        for n in [0, 1, 8, 9, 16, 17, 166] {
+            let v = bv(&(0..n).step_by(3).collect::<Vec<_>>(), n);
+            let got = BitVector::from_base64(&v.to_base64(), n).unwrap();
+            assert_eq!(got, v);
+            let got = BitVector::from_bytes(&v.to_bytes(), n).unwrap();
+            assert_eq!(got, v);
+        }
+
+        assert!(BitVector::from_hex("0102", 24).is_err());
+        assert!(BitVector::from_hex("zz", 8).is_err());
+        // bit 7 set in a 6-bit vector
+        assert!(BitVector::from_hex("80", 6).is_err());
+        assert!(BitVector::from_base64("AQI", 16).is_err());
+        assert_eq!(BitVector::from_base64("AQ==", 8).unwrap(), bv(&[0], 8));
+        assert!(BitVector::from_base64("AQ==AQ==", 16).is_err());
+    }
+}
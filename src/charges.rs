@@ -0,0 +1,98 @@
+//! Partial charges.
+
+use std::{ffi::c_int, fmt::Display};
+
+use crate::{RDError, ROMol};
+
+/// An error from [ROMol::compute_gasteiger_charges].
+#[derive(Debug)]
+pub enum ChargeError {
+    /// RDKit threw an exception, which was printed to stderr
+    RDKit(RDError),
+    /// the atoms, by index, whose elements lack Gasteiger parameters
+    MissingParameters(Vec<usize>),
+}
+
+impl Display for ChargeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChargeError::RDKit(e) => e.fmt(f),
+            ChargeError::MissingParameters(atoms) => {
+                write!(f, "missing Gasteiger parameters for atoms {atoms:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChargeError {}
+
+impl From<RDError> for ChargeError {
+    fn from(e: RDError) -> Self {
+        Self::RDKit(e)
+    }
+}
+
+impl ROMol {
+    /// Compute Gasteiger-Marsili partial charges for every atom in `self`,
+    /// using `n_iter` iterations of charge equalization (RDKit uses 12 by
+    /// default). The charges are also stored on each atom in the
+    /// `_GasteigerCharge` property, as in RDKit.
+    ///
+    /// Hydrogens only receive their own charges if they are explicit, so call
+    /// [ROMol::add_hs] first if you need them. Returns
+    /// [ChargeError::MissingParameters] with the indices of the offending
+    /// atoms if any of the elements in `self` lack Gasteiger parameters.
+    pub fn compute_gasteiger_charges(
+        &mut self,
+        n_iter: usize,
+    ) -> Result<Vec<f64>, ChargeError> {
+        // the C wrapper returns null if RDKit throws, after printing the
+        // exception, and otherwise one charge per atom
+        let charges = unsafe {
+            let mut len = 0;
+            let ptr = rdkit_sys::RDKit_ComputeGasteigerCharges(
+                self.0,
+                n_iter as c_int,
+                &mut len,
+            );
+            if ptr.is_null() {
+                return Err(RDError.into());
+            }
+            Vec::from_raw_parts(ptr, len, len)
+        };
+        // RDKit assigns NaN to atoms without parameters instead of throwing
+        let missing: Vec<_> = charges
+            .iter()
+            .enumerate()
+            .filter(|(_, q)| q.is_nan())
+            .map(|(i, _)| i)
+            .collect();
+        if !missing.is_empty() {
+            return Err(ChargeError::MissingParameters(missing));
+        }
+        Ok(charges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neutral_total_charge() {
+        let mut mol = ROMol::from_smiles("CC(=O)O");
+        mol.add_hs();
+        let charges = mol.compute_gasteiger_charges(12).unwrap();
+        assert_eq!(charges.len(), mol.num_atoms());
+        assert!(charges.iter().sum::<f64>().abs() < 1e-6);
+        // the carbonyl oxygen is more negative than the carbonyl carbon
+        assert!(charges[2] < charges[1]);
+    }
+
+    #[test]
+    fn unsupported_element() {
+        let mut mol = ROMol::from_smiles("[Xe]");
+        let err = mol.compute_gasteiger_charges(12).unwrap_err();
+        assert!(matches!(err, ChargeError::MissingParameters(a) if a == [0]));
+    }
+}
@@ -0,0 +1,108 @@
+//! Butina clustering, primarily for selecting representative conformers.
+
+use crate::ROMol;
+
+/// Cluster `n` points using the Butina algorithm (J. Chem. Inf. Comput. Sci.
+/// 1999, 39, 747), given the symmetric matrix of distances between them.
+///
+/// Points within `threshold` of each other are neighbors. The point with the
+/// most neighbors becomes the centroid of the first cluster, which also
+/// contains all of its neighbors. These points are removed, and the process
+/// repeats until every point is in a cluster. As in RDKit, ties are broken in
+/// favor of the point with the larger index. The first element of each
+/// returned cluster is its centroid.
+pub fn butina(dists: &[Vec<f64>], threshold: f64) -> Vec<Vec<usize>> {
+    let n = dists.len();
+    let neighbors: Vec<Vec<usize>> = (0..n)
+        .map(|i| {
+            (0..n)
+                .filter(|&j| j != i && dists[i][j] <= threshold)
+                .collect()
+        })
+        .collect();
+    let mut order: Vec<_> = (0..n).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse((neighbors[i].len(), i)));
+
+    let mut seen = vec![false; n];
+    let mut ret = Vec::new();
+    for idx in order {
+        if seen[idx] {
+            continue;
+        }
+        seen[idx] = true;
+        let mut cluster = vec![idx];
+        for &nbr in &neighbors[idx] {
+            if !seen[nbr] {
+                seen[nbr] = true;
+                cluster.push(nbr);
+            }
+        }
+        ret.push(cluster);
+    }
+    ret
+}
+
+/// The distance between conformers to use for [ROMol::cluster_conformers].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConformerMetric {
+    /// RMSD after aligning each pair of conformers, see
+    /// [ROMol::conformer_rms_matrix]
+    Rmsd,
+    /// Torsion Fingerprint Deviation, see [ROMol::tfd_matrix]
+    Tfd { use_weights: bool },
+}
+
+impl ROMol {
+    /// Cluster the conformers of `self` with [butina], returning clusters of
+    /// conformer IDs with the centroid of each cluster first. Taking the first
+    /// conformer of each cluster gives a diverse set of representatives.
+    ///
+    /// Computing RMSDs aligns the conformers, so this takes `&mut self`.
+    pub fn cluster_conformers(
+        &mut self,
+        metric: ConformerMetric,
+        threshold: f64,
+    ) -> Vec<Vec<usize>> {
+        let dists = match metric {
+            ConformerMetric::Rmsd => self.conformer_rms_matrix(None, false),
+            ConformerMetric::Tfd { use_weights } => {
+                self.tfd_matrix(use_weights)
+            }
+        };
+        let ids = self.conformer_ids();
+        butina(&dists, threshold)
+            .into_iter()
+            .map(|c| c.into_iter().map(|i| ids[i]).collect())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::embedded;
+
+    #[test]
+    fn butina_clusters() {
+        // points on a line at 0, 1, 2, 10, 11
+        let xs = [0.0, 1.0, 2.0, 10.0, 11.0f64];
+        let dists: Vec<Vec<f64>> = xs
+            .iter()
+            .map(|a| xs.iter().map(|b| (a - b).abs()).collect())
+            .collect();
+        let got = butina(&dists, 1.5);
+        assert_eq!(got, vec![vec![1, 0, 2], vec![4, 3]]);
+    }
+
+    #[test]
+    fn cluster_conformers() {
+        let mut mol = embedded("CCCCCO", 10);
+        let clusters = mol.cluster_conformers(
+            ConformerMetric::Tfd { use_weights: true },
+            0.1,
+        );
+        let mut all: Vec<_> = clusters.into_iter().flatten().collect();
+        all.sort();
+        assert_eq!(all, mol.conformer_ids());
+    }
+}
@@ -0,0 +1,165 @@
+//! Access to the conformers owned by an [ROMol].
+//!
+//! Conformers belong to their molecule, so [ROMol::get_conformer] returns a
+//! [Conformer] that borrows the molecule, and [ROMol::get_conformer_mut]
+//! returns a [ConformerMut] that can be used to update coordinates in place.
+
+use std::{
+    ffi::{c_int, c_uint},
+    marker::PhantomData,
+    ops::Deref,
+};
+
+use crate::{Point3D, ROMol};
+
+pub mod geometry;
+
+/// A read-only view of one of the conformers of an [ROMol].
+pub struct Conformer<'a> {
+    inner: *mut rdkit_sys::RDKit_Conformer,
+    _mol: PhantomData<&'a ROMol>,
+}
+
+impl Conformer<'_> {
+    pub fn get_positions(&self) -> Vec<Point3D> {
+        unsafe {
+            let mut n = 0;
+            let ps =
+                rdkit_sys::RDKit_Conformer_getPositions(self.inner, &mut n);
+            assert!(!ps.is_null());
+            Vec::from_raw_parts(ps, n as usize, n as usize)
+        }
+    }
+
+    pub fn get_atom_position(&self, atom: usize) -> Point3D {
+        assert!(atom < self.num_atoms(), "atom index out of range");
+        unsafe {
+            rdkit_sys::RDKit_Conformer_getAtomPos(self.inner, atom as c_uint)
+        }
+    }
+
+    pub fn num_atoms(&self) -> usize {
+        unsafe { rdkit_sys::RDKit_Conformer_getNumAtoms(self.inner) as usize }
+    }
+
+    /// The ID of this conformer within its molecule.
+    pub fn id(&self) -> usize {
+        unsafe { rdkit_sys::RDKit_Conformer_getId(self.inner) as usize }
+    }
+
+    /// Reports whether the conformer has 3D coordinates, as opposed to 2D
+    /// coordinates like those from [ROMol::compute_2d_coords].
+    pub fn is_3d(&self) -> bool {
+        unsafe { rdkit_sys::RDKit_Conformer_is3D(self.inner) }
+    }
+}
+
+/// A mutable view of one of the conformers of an [ROMol]. All of the read-only
+/// methods of [Conformer] are available through [Deref].
+pub struct ConformerMut<'a> {
+    conf: Conformer<'a>,
+}
+
+impl<'a> Deref for ConformerMut<'a> {
+    type Target = Conformer<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conf
+    }
+}
+
+impl ConformerMut<'_> {
+    /// Replace every atomic position in the conformer. Panics if
+    /// `positions.len()` does not match the number of atoms.
+    pub fn set_positions(&mut self, positions: &[Point3D]) {
+        assert_eq!(positions.len(), self.num_atoms());
+        unsafe {
+            rdkit_sys::RDKit_Conformer_setPositions(
+                self.conf.inner,
+                positions.as_ptr(),
+                positions.len(),
+            );
+        }
+    }
+
+    pub fn set_atom_position(&mut self, atom: usize, pos: Point3D) {
+        assert!(atom < self.num_atoms(), "atom index out of range");
+        unsafe {
+            rdkit_sys::RDKit_Conformer_setAtomPos(
+                self.conf.inner,
+                atom as c_uint,
+                pos,
+            );
+        }
+    }
+
+    pub fn set_3d(&mut self, is_3d: bool) {
+        unsafe {
+            rdkit_sys::RDKit_Conformer_set3D(self.conf.inner, is_3d);
+        }
+    }
+}
+
+impl ROMol {
+    fn conformer_ptr(&self, id: usize) -> *mut rdkit_sys::RDKit_Conformer {
+        let ptr =
+            unsafe { rdkit_sys::RDKit_ROMol_getConformer(self.0, id as c_int) };
+        assert!(!ptr.is_null(), "no conformer with id {id}");
+        ptr
+    }
+
+    /// Borrow the conformer with ID `id`. Panics if there is no such
+    /// conformer.
+    pub fn get_conformer(&self, id: usize) -> Conformer<'_> {
+        Conformer { inner: self.conformer_ptr(id), _mol: PhantomData }
+    }
+
+    /// Mutably borrow the conformer with ID `id`. Panics if there is no such
+    /// conformer.
+    pub fn get_conformer_mut(&mut self, id: usize) -> ConformerMut<'_> {
+        let inner = self.conformer_ptr(id);
+        ConformerMut { conf: Conformer { inner, _mol: PhantomData } }
+    }
+
+    pub fn num_conformers(&self) -> usize {
+        unsafe { rdkit_sys::RDKit_ROMol_getNumConformers(self.0) as usize }
+    }
+
+    /// The IDs of all of the conformers of `self`, in the order they are
+    /// stored. IDs are not necessarily contiguous after conformers have been
+    /// removed.
+    pub fn conformer_ids(&self) -> Vec<usize> {
+        unsafe {
+            let mut len = 0;
+            let ids = rdkit_sys::RDKit_ROMol_getConformerIds(self.0, &mut len);
+            let ids = Vec::from_raw_parts(ids, len, len);
+            ids.into_iter().map(|i| i as usize).collect()
+        }
+    }
+
+    /// Add a new 3D conformer with the given `positions`, returning its ID.
+    /// Panics if `positions.len()` does not match the number of atoms.
+    pub fn add_conformer(&mut self, positions: &[Point3D]) -> usize {
+        assert_eq!(positions.len(), self.num_atoms());
+        unsafe {
+            rdkit_sys::RDKit_ROMol_addConformer(
+                self.0,
+                positions.as_ptr(),
+                positions.len(),
+            ) as usize
+        }
+    }
+
+    /// Remove the conformer with ID `id`, if it exists.
+    pub fn remove_conformer(&mut self, id: usize) {
+        unsafe {
+            rdkit_sys::RDKit_ROMol_removeConformer(self.0, id as c_uint);
+        }
+    }
+
+    pub fn clear_conformers(&mut self) {
+        unsafe {
+            rdkit_sys::RDKit_ROMol_clearConformers(self.0);
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! Internal coordinates and other geometric properties of conformers.
+
+use std::ffi::c_uint;
+
+use crate::{Point3D, RDError};
+
+use super::{Conformer, ConformerMut};
+
+fn sub(a: &Point3D, b: &Point3D) -> [f64; 3] {
+    [a.x - b.x, a.y - b.y, a.z - b.z]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn norm(a: [f64; 3]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn distance(a: &Point3D, b: &Point3D) -> f64 {
+    norm(sub(a, b))
+}
+
+/// the angle a-b-c in degrees
+fn angle(a: &Point3D, b: &Point3D, c: &Point3D) -> f64 {
+    let u = sub(a, b);
+    let v = sub(c, b);
+    let cos = dot(u, v) / (norm(u) * norm(v));
+    cos.clamp(-1.0, 1.0).acos().to_degrees()
+}
+
+/// the dihedral a-b-c-d in degrees, in the range (-180, 180]
+fn dihedral(a: &Point3D, b: &Point3D, c: &Point3D, d: &Point3D) -> f64 {
+    let b1 = sub(b, a);
+    let b2 = sub(c, b);
+    let b3 = sub(d, c);
+    let n1 = cross(b1, b2);
+    let n2 = cross(b2, b3);
+    let x = dot(n1, n2) * norm(b2);
+    let y = dot(cross(n1, n2), b2);
+    let deg = y.atan2(x).to_degrees();
+    // keep the result in (-180, 180] when atan2 returns -180 for y = -0.0
+    if deg <= -180.0 {
+        deg + 360.0
+    } else {
+        deg
+    }
+}
+
+/// The principal axes of a conformer and their moments of inertia, sorted by
+/// increasing moment.
+#[derive(Clone, Copy)]
+pub struct PrincipalAxes {
+    pub axes: [Point3D; 3],
+    pub moments: [f64; 3],
+}
+
+impl Conformer<'_> {
+    /// The distance between atoms `i` and `j` in Å.
+    pub fn get_bond_length(&self, i: usize, j: usize) -> f64 {
+        distance(&self.get_atom_position(i), &self.get_atom_position(j))
+    }
+
+    /// The angle formed by atoms `i`, `j`, and `k` in degrees.
+    pub fn get_angle_deg(&self, i: usize, j: usize, k: usize) -> f64 {
+        angle(
+            &self.get_atom_position(i),
+            &self.get_atom_position(j),
+            &self.get_atom_position(k),
+        )
+    }
+
+    /// The dihedral angle formed by atoms `i`, `j`, `k`, and `l` in degrees,
+    /// in the range (-180, 180].
+    pub fn get_dihedral_deg(
+        &self,
+        i: usize,
+        j: usize,
+        k: usize,
+        l: usize,
+    ) -> f64 {
+        dihedral(
+            &self.get_atom_position(i),
+            &self.get_atom_position(j),
+            &self.get_atom_position(k),
+            &self.get_atom_position(l),
+        )
+    }
+
+    /// The unweighted center of all of the atoms in the conformer.
+    pub fn centroid(&self) -> Point3D {
+        let pos = self.get_positions();
+        let n = pos.len() as f64;
+        let (x, y, z) = pos
+            .iter()
+            .fold((0.0, 0.0, 0.0), |(x, y, z), p| (x + p.x, y + p.y, z + p.z));
+        Point3D { x: x / n, y: y / n, z: z / n }
+    }
+
+    /// The matrix of distances between every pair of atoms in Å.
+    pub fn distance_matrix(&self) -> Vec<Vec<f64>> {
+        let pos = self.get_positions();
+        pos.iter()
+            .map(|a| pos.iter().map(|b| distance(a, b)).collect())
+            .collect()
+    }
+
+    /// Compute the principal axes and moments of inertia of the conformer,
+    /// using atomic masses as weights. Hydrogens are skipped if `ignore_hs` is
+    /// true. Returns an error if RDKit fails to diagonalize the inertia
+    /// tensor.
+    pub fn principal_axes(
+        &self,
+        ignore_hs: bool,
+    ) -> Result<PrincipalAxes, RDError> {
+        let origin = Point3D { x: 0.0, y: 0.0, z: 0.0 };
+        let mut axes = [origin; 3];
+        let mut moments = [0.0; 3];
+        let ok = unsafe {
+            rdkit_sys::RDKit_Conformer_computePrincipalAxesAndMoments(
+                self.inner,
+                ignore_hs,
+                axes.as_mut_ptr(),
+                moments.as_mut_ptr(),
+            )
+        };
+        if !ok {
+            return Err(RDError);
+        }
+        Ok(PrincipalAxes { axes, moments })
+    }
+}
+
+impl ConformerMut<'_> {
+    /// Set the distance between bonded atoms `i` and `j` to `value` Å by
+    /// moving the atoms on the `j` side of the bond. Returns an error if the
+    /// atoms are not bonded or the bond is in a ring.
+    pub fn set_bond_length(
+        &mut self,
+        i: usize,
+        j: usize,
+        value: f64,
+    ) -> Result<(), RDError> {
+        let ok = unsafe {
+            rdkit_sys::RDKit_Conformer_setBondLength(
+                self.conf.inner,
+                i as c_uint,
+                j as c_uint,
+                value,
+            )
+        };
+        if !ok {
+            return Err(RDError);
+        }
+        Ok(())
+    }
+
+    /// Set the angle i-j-k to `value` degrees by moving the atoms on the `k`
+    /// side of the j-k bond. Returns an error if the atoms are not bonded or
+    /// the j-k bond is in a ring.
+    pub fn set_angle_deg(
+        &mut self,
+        i: usize,
+        j: usize,
+        k: usize,
+        value: f64,
+    ) -> Result<(), RDError> {
+        let ok = unsafe {
+            rdkit_sys::RDKit_Conformer_setAngleDeg(
+                self.conf.inner,
+                i as c_uint,
+                j as c_uint,
+                k as c_uint,
+                value,
+            )
+        };
+        if !ok {
+            return Err(RDError);
+        }
+        Ok(())
+    }
+
+    /// Set the dihedral i-j-k-l to `value` degrees by rotating the atoms on
+    /// the `l` side of the j-k bond. Returns an error if the atoms are not
+    /// bonded or the j-k bond is in a ring.
+    pub fn set_dihedral_deg(
+        &mut self,
+        i: usize,
+        j: usize,
+        k: usize,
+        l: usize,
+        value: f64,
+    ) -> Result<(), RDError> {
+        let ok = unsafe {
+            rdkit_sys::RDKit_Conformer_setDihedralDeg(
+                self.conf.inner,
+                i as c_uint,
+                j as c_uint,
+                k as c_uint,
+                l as c_uint,
+                value,
+            )
+        };
+        if !ok {
+            return Err(RDError);
+        }
+        Ok(())
+    }
+
+    /// Translate the conformer to its center of mass and rotate it so that
+    /// its principal axes line up with the x, y, and z axes.
+    pub fn canonicalize(&mut self) {
+        unsafe {
+            rdkit_sys::RDKit_Conformer_canonicalize(self.conf.inner);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ROMol;
+
+    use super::*;
+
+    fn p(x: f64, y: f64, z: f64) -> Point3D {
+        Point3D { x, y, z }
+    }
+
+    #[test]
+    fn internal_coordinates() {
+        let a = p(1.0, 0.0, 0.0);
+        let b = p(0.0, 0.0, 0.0);
+        let c = p(0.0, 1.0, 0.0);
+        assert!((distance(&a, &c) - 2f64.sqrt()).abs() < 1e-12);
+        assert!((angle(&a, &b, &c) - 90.0).abs() < 1e-12);
+
+        let d = p(0.0, 1.0, 1.0);
+        assert!((dihedral(&a, &b, &c, &d) + 90.0).abs() < 1e-12);
+        let d = p(0.0, 1.0, -1.0);
+        assert!((dihedral(&a, &b, &c, &d) - 90.0).abs() < 1e-12);
+        let d = p(-1.0, 1.0, 0.0);
+        assert!((dihedral(&a, &b, &c, &d) - 180.0).abs() < 1e-12);
+
+        // a trans dihedral where atan2 sees y = -0.0
+        let got = dihedral(
+            &p(-1.0, 1.0, 1.0),
+            &b,
+            &p(-1.0, 0.0, 0.0),
+            &p(-1.0, -1.0, -1.0),
+        );
+        assert_eq!(got, 180.0);
+    }
+
+    #[test]
+    fn set_dihedral() {
+        let mut mol = ROMol::from_smiles("CCCC");
+        mol.add_hs();
+        let id = mol.embed_molecule(&Default::default()).unwrap();
+        mol.get_conformer_mut(id)
+            .set_dihedral_deg(0, 1, 2, 3, 60.0)
+            .unwrap();
+        let got = mol.get_conformer(id).get_dihedral_deg(0, 1, 2, 3);
+        assert!((got - 60.0).abs() < 1e-6);
+    }
+}
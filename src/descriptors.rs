@@ -0,0 +1,202 @@
+//! Common molecular descriptors, mirroring RDKit's `Descriptors` module.
+//!
+//! Each descriptor is a free function taking the molecule, and [calc_all]
+//! computes all of the numeric ones at once, keyed by their RDKit names.
+
+use std::{collections::BTreeMap, ffi::CString};
+
+use crate::{
+    torsions::{RotatableBonds, TorsionParams},
+    ROMol,
+};
+
+/// The monoisotopic molecular weight of `mol`, using the mass of the most
+/// abundant isotope of each element.
+pub fn exact_mol_wt(mol: &ROMol) -> f64 {
+    unsafe { rdkit_sys::RDKit_CalcExactMW(mol.0) }
+}
+
+/// The average molecular weight of `mol`, using standard atomic weights.
+pub fn mol_wt(mol: &ROMol) -> f64 {
+    unsafe { rdkit_sys::RDKit_CalcAMW(mol.0) }
+}
+
+/// The molecular formula of `mol` in Hill order, like `C9H8O4` for aspirin.
+pub fn mol_formula(mol: &ROMol) -> String {
+    unsafe {
+        let s = rdkit_sys::RDKit_CalcMolFormula(mol.0);
+        CString::from_raw(s).to_str().unwrap().to_owned()
+    }
+}
+
+fn crippen(mol: &ROMol) -> (f64, f64) {
+    let mut logp = 0.0;
+    let mut mr = 0.0;
+    unsafe {
+        rdkit_sys::RDKit_CalcCrippenDescriptors(mol.0, &mut logp, &mut mr);
+    }
+    (logp, mr)
+}
+
+/// The Wildman-Crippen estimate of the octanol/water partition coefficient.
+pub fn mol_log_p(mol: &ROMol) -> f64 {
+    crippen(mol).0
+}
+
+/// The Wildman-Crippen estimate of the molar refractivity.
+pub fn mol_mr(mol: &ROMol) -> f64 {
+    crippen(mol).1
+}
+
+/// The topological polar surface area of Ertl et al., considering only N and
+/// O atoms.
+pub fn tpsa(mol: &ROMol) -> f64 {
+    unsafe { rdkit_sys::RDKit_CalcTPSA(mol.0) }
+}
+
+/// The number of hydrogen bond donors.
+pub fn num_h_donors(mol: &ROMol) -> usize {
+    unsafe { rdkit_sys::RDKit_CalcNumHBD(mol.0) as usize }
+}
+
+/// The number of hydrogen bond acceptors.
+pub fn num_h_acceptors(mol: &ROMol) -> usize {
+    unsafe { rdkit_sys::RDKit_CalcNumHBA(mol.0) as usize }
+}
+
+/// The number of hydrogen bond donors by Lipinski's original definition: the
+/// total number of hydrogens on N and O atoms. This is RDKit's
+/// `NumHDonorsLipinski`, also called `NHOHCount`.
+pub fn num_lipinski_hbd(mol: &ROMol) -> usize {
+    unsafe { rdkit_sys::RDKit_CalcNumLipinskiHBD(mol.0) as usize }
+}
+
+/// The number of hydrogen bond acceptors by Lipinski's original definition:
+/// the number of N and O atoms. This is RDKit's `NumHAcceptorsLipinski`, also
+/// called `NOCount`.
+pub fn num_lipinski_hba(mol: &ROMol) -> usize {
+    unsafe { rdkit_sys::RDKit_CalcNumLipinskiHBA(mol.0) as usize }
+}
+
+/// The number of rotatable bonds under `definition`. Unlike
+/// [ROMol::rotatable_bonds] with the default [TorsionParams], this counts
+/// bonds to methyl groups with explicit hydrogens, as RDKit does.
+pub fn num_rotatable_bonds(mol: &ROMol, definition: RotatableBonds) -> usize {
+    let params = TorsionParams {
+        definition,
+        terminal_methyls: true,
+        amides: true,
+        ..Default::default()
+    };
+    mol.rotatable_bonds(&params).len()
+}
+
+/// The number of rings in the smallest set of smallest rings.
+pub fn ring_count(mol: &ROMol) -> usize {
+    unsafe { rdkit_sys::RDKit_CalcNumRings(mol.0) as usize }
+}
+
+pub fn num_aromatic_rings(mol: &ROMol) -> usize {
+    unsafe { rdkit_sys::RDKit_CalcNumAromaticRings(mol.0) as usize }
+}
+
+/// The number of rings containing at least one non-aromatic bond.
+pub fn num_aliphatic_rings(mol: &ROMol) -> usize {
+    unsafe { rdkit_sys::RDKit_CalcNumAliphaticRings(mol.0) as usize }
+}
+
+/// The number of rings containing only single bonds.
+pub fn num_saturated_rings(mol: &ROMol) -> usize {
+    unsafe { rdkit_sys::RDKit_CalcNumSaturatedRings(mol.0) as usize }
+}
+
+/// The fraction of carbon atoms that are sp3 hybridized.
+pub fn fraction_csp3(mol: &ROMol) -> f64 {
+    unsafe { rdkit_sys::RDKit_CalcFractionCSP3(mol.0) }
+}
+
+pub fn heavy_atom_count(mol: &ROMol) -> usize {
+    unsafe { rdkit_sys::RDKit_CalcNumHeavyAtoms(mol.0) as usize }
+}
+
+/// The sum of the formal charges on every atom.
+pub fn formal_charge(mol: &ROMol) -> isize {
+    unsafe { rdkit_sys::RDKit_GetFormalCharge(mol.0) as isize }
+}
+
+/// The quantitative estimate of drug-likeness of Bickerton et al., between 0
+/// and 1, with the default weights.
+pub fn qed(mol: &ROMol) -> f64 {
+    unsafe { rdkit_sys::RDKit_CalcQED(mol.0) }
+}
+
+/// Labute's approximate surface area.
+pub fn labute_asa(mol: &ROMol) -> f64 {
+    unsafe { rdkit_sys::RDKit_CalcLabuteASA(mol.0) }
+}
+
+/// Compute every numeric descriptor in this module, keyed by its name in
+/// RDKit's `Descriptors` module. Rotatable bonds use the
+/// [RotatableBonds::Strict] definition. [mol_formula] is omitted since it is
+/// not a number.
+pub fn calc_all(mol: &ROMol) -> BTreeMap<&'static str, f64> {
+    let (logp, mr) = crippen(mol);
+    BTreeMap::from([
+        ("ExactMolWt", exact_mol_wt(mol)),
+        ("MolWt", mol_wt(mol)),
+        ("MolLogP", logp),
+        ("MolMR", mr),
+        ("TPSA", tpsa(mol)),
+        ("NumHDonors", num_h_donors(mol) as f64),
+        ("NumHAcceptors", num_h_acceptors(mol) as f64),
+        ("NHOHCount", num_lipinski_hbd(mol) as f64),
+        ("NOCount", num_lipinski_hba(mol) as f64),
+        (
+            "NumRotatableBonds",
+            num_rotatable_bonds(mol, RotatableBonds::Strict) as f64,
+        ),
+        ("RingCount", ring_count(mol) as f64),
+        ("NumAromaticRings", num_aromatic_rings(mol) as f64),
+        ("NumAliphaticRings", num_aliphatic_rings(mol) as f64),
+        ("NumSaturatedRings", num_saturated_rings(mol) as f64),
+        ("FractionCSP3", fraction_csp3(mol)),
+        ("HeavyAtomCount", heavy_atom_count(mol) as f64),
+        ("FormalCharge", formal_charge(mol) as f64),
+        ("qed", qed(mol)),
+        ("LabuteASA", labute_asa(mol)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASPIRIN: &str = "CC(=O)Oc1ccccc1C(=O)O";
+
+    #[test]
+    fn aspirin() {
+        let mol = ROMol::from_smiles(ASPIRIN);
+        assert_eq!(mol_formula(&mol), "C9H8O4");
+        assert!((exact_mol_wt(&mol) - 180.042).abs() < 1e-3);
+        assert!((mol_wt(&mol) - 180.159).abs() < 1e-3);
+        assert!((tpsa(&mol) - 63.6).abs() < 1e-2);
+        assert_eq!(num_h_donors(&mol), 1);
+        assert_eq!(num_lipinski_hbd(&mol), 1);
+        assert_eq!(num_lipinski_hba(&mol), 4);
+        assert_eq!(num_rotatable_bonds(&mol, RotatableBonds::NonStrict), 3);
+        assert_eq!(num_aromatic_rings(&mol), 1);
+        assert_eq!(num_aliphatic_rings(&mol), 0);
+        assert_eq!(heavy_atom_count(&mol), 13);
+        assert_eq!(formal_charge(&mol), 0);
+        assert!((0.0..=1.0).contains(&qed(&mol)));
+    }
+
+    #[test]
+    fn all() {
+        let mol = ROMol::from_smiles(ASPIRIN);
+        let got = calc_all(&mol);
+        assert_eq!(got.len(), 19);
+        assert_eq!(got["HeavyAtomCount"], 13.0);
+        assert_eq!(got["FractionCSP3"], fraction_csp3(&mol));
+    }
+}
@@ -0,0 +1,136 @@
+//! 3D conformer generation using RDKit's distance geometry embedding.
+
+use std::ffi::{c_int, c_uint};
+
+use crate::{RDError, ROMol};
+
+/// The variant of distance geometry used for embedding, corresponding to the
+/// parameter presets in RDKit's `DGeomHelpers`. The discriminants are the
+/// values the C wrapper uses to select a preset, so they must not change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[repr(u32)]
+pub enum EmbedVersion {
+    /// plain experimental torsion distance geometry
+    ETDG = 0,
+    /// distance geometry with basic knowledge terms
+    KDG = 1,
+    ETKDG = 2,
+    ETKDGv2 = 3,
+    #[default]
+    ETKDGv3 = 4,
+    /// ETKDGv3 with the small ring torsions enabled
+    SrETKDGv3 = 5,
+}
+
+pub struct EmbedParameters {
+    /// defaults to ETKDGv3
+    pub version: EmbedVersion,
+    /// defaults to -1, meaning a random seed is not set
+    pub random_seed: i32,
+    /// conformers closer than this heavy atom RMSD to a previously generated
+    /// conformer are discarded. defaults to -1, meaning no pruning
+    pub prune_rms_thresh: f64,
+    /// the maximum number of embedding attempts per conformer. defaults to 0,
+    /// meaning RDKit chooses based on the molecule size
+    pub max_iterations: u32,
+    /// the number of threads to use when embedding multiple conformers. as in
+    /// RDKit, 0 uses all available threads and a negative value -n uses all
+    /// but n of them. defaults to 1
+    pub num_threads: i32,
+    /// start from random coordinates instead of eigenvalues of the distance
+    /// matrix. defaults to false
+    pub use_random_coords: bool,
+    /// defaults to true
+    pub enforce_chirality: bool,
+}
+
+impl Default for EmbedParameters {
+    fn default() -> Self {
+        Self {
+            version: EmbedVersion::default(),
+            random_seed: -1,
+            prune_rms_thresh: -1.0,
+            max_iterations: 0,
+            num_threads: 1,
+            use_random_coords: false,
+            enforce_chirality: true,
+        }
+    }
+}
+
+impl ROMol {
+    /// Generate a single 3D conformer for `self` and return its conformer ID
+    /// for use with [ROMol::get_conformer]. Hydrogens should usually be added
+    /// with [ROMol::add_hs] first. Returns an error if embedding fails.
+    pub fn embed_molecule(
+        &mut self,
+        params: &EmbedParameters,
+    ) -> Result<usize, RDError> {
+        let id = unsafe {
+            rdkit_sys::RDKit_EmbedMolecule(
+                self.0,
+                params.version as c_uint,
+                params.random_seed as c_int,
+                params.max_iterations as c_uint,
+                params.use_random_coords,
+                params.enforce_chirality,
+            )
+        };
+        if id < 0 {
+            return Err(RDError);
+        }
+        Ok(id as usize)
+    }
+
+    /// Generate up to `num_confs` 3D conformers for `self`, returning the IDs
+    /// of the conformers that were successfully embedded and survived pruning.
+    /// Any existing conformers are removed.
+    pub fn embed_multiple_confs(
+        &mut self,
+        num_confs: usize,
+        params: &EmbedParameters,
+    ) -> Vec<usize> {
+        unsafe {
+            let mut len = 0;
+            let ids = rdkit_sys::RDKit_EmbedMultipleConfs(
+                self.0,
+                num_confs as c_uint,
+                params.version as c_uint,
+                params.random_seed as c_int,
+                params.prune_rms_thresh,
+                params.max_iterations as c_uint,
+                params.num_threads as c_int,
+                params.use_random_coords,
+                params.enforce_chirality,
+                &mut len,
+            );
+            let ids = Vec::from_raw_parts(ids, len, len);
+            ids.into_iter().map(|i| i as usize).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embed_ethanol() {
+        let mut mol = ROMol::from_smiles("CCO");
+        mol.add_hs();
+        let params = EmbedParameters { random_seed: 42, ..Default::default() };
+        let id = mol.embed_molecule(&params).unwrap();
+        let pos = mol.get_conformer(id).get_positions();
+        assert_eq!(pos.len(), 9);
+        assert!(pos.iter().any(|p| p.z.abs() > 1e-3));
+    }
+
+    #[test]
+    fn embed_multiple() {
+        let mut mol = ROMol::from_smiles("CCCCO");
+        mol.add_hs();
+        let params = EmbedParameters { random_seed: 42, ..Default::default() };
+        let ids = mol.embed_multiple_confs(5, &params);
+        assert_eq!(ids, [0, 1, 2, 3, 4]);
+    }
+}
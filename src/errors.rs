@@ -11,9 +11,24 @@ impl Display for RDError {
 
 impl std::error::Error for RDError {}
 
+#[cfg(feature = "serde")]
 impl From<serde_json::Error> for RDError {
     fn from(e: serde_json::Error) -> Self {
         eprintln!("{e:?}");
         Self
     }
 }
+
+impl From<roxmltree::Error> for RDError {
+    fn from(e: roxmltree::Error) -> Self {
+        eprintln!("{e:?}");
+        Self
+    }
+}
+
+impl From<std::io::Error> for RDError {
+    fn from(e: std::io::Error) -> Self {
+        eprintln!("{e:?}");
+        Self
+    }
+}
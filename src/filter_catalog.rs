@@ -0,0 +1,248 @@
+//! Structural alerts from RDKit's `FilterCatalog` and user-defined SMARTS.
+//!
+//! A [FilterCatalog] is built from any combination of RDKit's built-in alert
+//! sets, selected with [FilterCatalogs], and custom entries added from SMARTS
+//! patterns or read from a file with [FilterCatalog::add_smarts_file]:
+//!
+//! ```text
+//! let mut catalog = FilterCatalog::new(FilterCatalogs::PAINS | FilterCatalogs::BRENK)?;
+//! catalog.add_smarts_file("alerts.txt")?;
+//! for m in catalog.get_matches(&mol) {
+//!     println!("{}: {:?}", m.description, m.atoms);
+//! }
+//! ```
+
+use std::{
+    ffi::{c_uint, CString},
+    path::Path,
+};
+
+use bitflags::bitflags;
+
+use crate::{RDError, ROMol, SmartsPattern};
+
+bitflags! {
+    /// RDKit's built-in alert sets.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct FilterCatalogs: c_uint {
+        const PAINS_A =             0x1;
+        const PAINS_B =             0x2;
+        const PAINS_C =             0x4;
+        const PAINS =               0x7;
+        const BRENK =               0x8;
+        const NIH =                 0x10;
+        const ZINC =                0x20;
+        const CHEMBL_GLAXO =        0x40;
+        const CHEMBL_DUNDEE =       0x80;
+        const CHEMBL_BMS =          0x100;
+        const CHEMBL_SURECHEMBL =   0x200;
+        const CHEMBL_MLSMR =        0x400;
+        const CHEMBL_INPHARMATICA = 0x800;
+        const CHEMBL_LINT =         0x1000;
+        const CHEMBL =              0x1FC0;
+        const ALL =                 0x1FFF;
+    }
+}
+
+/// A single alert that matched a molecule.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FilterMatch {
+    /// the description of the catalog entry, such as `"quinone_A(370)"` for
+    /// PAINS
+    pub description: String,
+    /// the indices of the atoms in the molecule matched by the alert
+    pub atoms: Vec<usize>,
+}
+
+struct CustomEntry {
+    description: String,
+    pattern: SmartsPattern,
+}
+
+/// A collection of structural alerts to screen molecules against.
+pub struct FilterCatalog {
+    builtin: *mut rdkit_sys::RDKit_FilterCatalog,
+    custom: Vec<CustomEntry>,
+}
+
+unsafe impl Send for FilterCatalog {}
+unsafe impl Sync for FilterCatalog {}
+
+/// Split a line of a SMARTS alert file into the SMARTS pattern and its
+/// description, returning `None` for blank lines and comments.
+fn parse_line(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    match line.split_once(char::is_whitespace) {
+        Some((smarts, desc)) => Some((smarts, desc.trim())),
+        None => Some((line, line)),
+    }
+}
+
+impl FilterCatalog {
+    /// Create a catalog containing the built-in alert sets in `catalogs`. Use
+    /// [FilterCatalogs::empty] for a catalog with only custom entries.
+    /// Returns an error if RDKit fails to build the catalog, in which case
+    /// the C wrapper returns null after printing the exception to stderr.
+    pub fn new(catalogs: FilterCatalogs) -> Result<Self, RDError> {
+        let builtin =
+            unsafe { rdkit_sys::RDKit_FilterCatalog_new(catalogs.bits()) };
+        if builtin.is_null() {
+            return Err(RDError);
+        }
+        Ok(Self { builtin, custom: Vec::new() })
+    }
+
+    /// Add a custom entry matching `smarts`. Returns an error if `smarts`
+    /// cannot be parsed.
+    pub fn add_smarts(
+        &mut self,
+        description: impl Into<String>,
+        smarts: &str,
+    ) -> Result<(), RDError> {
+        let pattern = SmartsPattern::new(smarts)?;
+        self.custom
+            .push(CustomEntry { description: description.into(), pattern });
+        Ok(())
+    }
+
+    /// Add a custom entry for each line of the file at `path`. Each line
+    /// contains a SMARTS pattern, optionally followed by whitespace and a
+    /// description, which defaults to the SMARTS itself. Blank lines and
+    /// lines starting with `#` are skipped. Returns an error if the file
+    /// cannot be read or any of the patterns cannot be parsed, in which case
+    /// no entries are added.
+    pub fn add_smarts_file(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> Result<(), RDError> {
+        let contents = std::fs::read_to_string(path)?;
+        let mut entries = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            let Some((smarts, description)) = parse_line(line) else {
+                continue;
+            };
+            let Ok(pattern) = SmartsPattern::new(smarts) else {
+                eprintln!("failed to parse SMARTS on line {}: {smarts}", i + 1);
+                return Err(RDError);
+            };
+            entries.push(CustomEntry {
+                description: description.to_owned(),
+                pattern,
+            });
+        }
+        self.custom.extend(entries);
+        Ok(())
+    }
+
+    /// The total number of built-in and custom entries.
+    pub fn num_entries(&self) -> usize {
+        let builtin = unsafe {
+            rdkit_sys::RDKit_FilterCatalog_getNumEntries(self.builtin) as usize
+        };
+        builtin + self.custom.len()
+    }
+
+    /// Returns every alert matching `mol`, with the built-in entries first.
+    /// An entry that matches in several places is reported once for each
+    /// match.
+    pub fn get_matches(&self, mol: &ROMol) -> Vec<FilterMatch> {
+        let mut ret = unsafe {
+            let mut len = 0;
+            let ptr = rdkit_sys::RDKit_FilterCatalog_getMatches(
+                self.builtin,
+                mol.0,
+                &mut len,
+            );
+            let matches = Vec::from_raw_parts(ptr, len, len);
+            matches
+                .into_iter()
+                .map(|m| {
+                    let atoms =
+                        Vec::from_raw_parts(m.atoms, m.num_atoms, m.num_atoms);
+                    FilterMatch {
+                        description: CString::from_raw(m.description)
+                            .to_str()
+                            .unwrap()
+                            .to_owned(),
+                        atoms: atoms.into_iter().map(|a| a as usize).collect(),
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+        for entry in &self.custom {
+            for atoms in entry.pattern.matches(mol) {
+                ret.push(FilterMatch {
+                    description: entry.description.clone(),
+                    atoms,
+                });
+            }
+        }
+        ret
+    }
+
+    /// Reports whether any entry in the catalog matches `mol`.
+    pub fn has_match(&self, mol: &ROMol) -> bool {
+        let builtin = unsafe {
+            rdkit_sys::RDKit_FilterCatalog_hasMatch(self.builtin, mol.0)
+        };
+        builtin || self.custom.iter().any(|e| e.pattern.is_match(mol))
+    }
+}
+
+impl Drop for FilterCatalog {
+    fn drop(&mut self) {
+        unsafe {
+            rdkit_sys::RDKit_FilterCatalog_delete(self.builtin);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_lines() {
+        assert_eq!(parse_line("  # comment"), None);
+        assert_eq!(parse_line(""), None);
+        assert_eq!(parse_line("[SH] thiol"), Some(("[SH]", "thiol")));
+        assert_eq!(
+            parse_line("O=C1C=CC(=O)C=C1\tpara quinone "),
+            Some(("O=C1C=CC(=O)C=C1", "para quinone"))
+        );
+        assert_eq!(
+            parse_line("[N+](=O)[O-]"),
+            Some(("[N+](=O)[O-]", "[N+](=O)[O-]"))
+        );
+    }
+
+    #[test]
+    fn pains() {
+        let catalog = FilterCatalog::new(FilterCatalogs::PAINS).unwrap();
+        assert!(catalog.num_entries() > 400);
+        // a para-quinone, which is flagged by PAINS A
+        let mol = ROMol::from_smiles("O=C1C=CC(=O)C=C1");
+        assert!(catalog.has_match(&mol));
+        let mol = ROMol::from_smiles("CCO");
+        assert!(catalog.get_matches(&mol).is_empty());
+    }
+
+    #[test]
+    fn custom() {
+        let mut catalog = FilterCatalog::new(FilterCatalogs::empty()).unwrap();
+        catalog.add_smarts_file("testfiles/alerts.txt").unwrap();
+        assert_eq!(catalog.num_entries(), 2);
+        let mol = ROMol::from_smiles("CCS");
+        let got = catalog.get_matches(&mol);
+        assert_eq!(
+            got,
+            vec![FilterMatch {
+                description: "thiol".to_owned(),
+                atoms: vec![2]
+            }]
+        );
+    }
+}
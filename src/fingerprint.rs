@@ -1,4 +1,78 @@
+use std::{collections::HashMap, ffi::c_uint};
+
+use bitflags::bitflags;
+
 use super::bitvector::BitVector;
+use crate::ROMol;
+
+pub mod fps;
+pub mod generators;
+pub mod index;
+pub mod morgan;
+pub mod search;
+
+pub use generators::{
+    AtomPairGenerator, RDKitFPGenerator, TopologicalTorsionGenerator,
+};
+pub use morgan::MorganGenerator;
+
+/// The number of bits in a MACCS key fingerprint. Bit 0 is unused, so the 166
+/// keys occupy bits 1 through 166.
+pub const MACCS_SIZE: usize = 167;
+
+bitflags! {
+    /// The layers to include in a [layered_fingerprint].
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct LayerFlags: c_uint {
+        const PURE_TOPOLOGY = 0x1;
+        const BOND_ORDER =    0x2;
+        const ATOM_TYPES =    0x4;
+        const RING_PRESENCE = 0x8;
+        const RING_SIZE =     0x10;
+        const AROMATICITY =   0x20;
+        const ALL =           0xFFFFFFFF;
+    }
+}
+
+/// The 166 public MACCS structural keys.
+pub fn maccs_keys(mol: &ROMol) -> BitVector {
+    let mut tmp = [false; MACCS_SIZE];
+    unsafe {
+        rdkit_sys::RDKit_MACCSFingerprint(mol.0, tmp.as_mut_ptr());
+    }
+    tmp.as_ref().into()
+}
+
+/// RDKit's pattern fingerprint of `fp_size` bits, designed for substructure
+/// screening: every bit set in a query's fingerprint is also set in the
+/// fingerprints of molecules containing it.
+pub fn pattern_fingerprint(mol: &ROMol, fp_size: usize) -> BitVector {
+    let mut tmp = vec![false; fp_size];
+    unsafe {
+        rdkit_sys::RDKit_PatternFingerprint(mol.0, fp_size, tmp.as_mut_ptr());
+    }
+    tmp.as_slice().into()
+}
+
+/// RDKit's layered fingerprint of `fp_size` bits, a path-based fingerprint
+/// combining the properties in `layers`. Like [pattern_fingerprint], this is
+/// suitable for substructure screening.
+pub fn layered_fingerprint(
+    mol: &ROMol,
+    layers: LayerFlags,
+    fp_size: usize,
+) -> BitVector {
+    let mut tmp = vec![false; fp_size];
+    unsafe {
+        rdkit_sys::RDKit_LayeredFingerprint(
+            mol.0,
+            layers.bits(),
+            fp_size,
+            tmp.as_mut_ptr(),
+        );
+    }
+    tmp.as_slice().into()
+}
 
 /// print `bv` to stdout in 16 groups of 4 per row
 pub fn print_bit_vec(bv: &[usize]) {
@@ -13,19 +87,208 @@ pub fn print_bit_vec(bv: &[usize]) {
     }
 }
 
-/// return the number of bits in the intersection of a and b
+/// return the number of bits in the intersection of a and b. Panics if a and
+/// b have different numbers of bits, like the [BitVector] operators.
 pub fn intersect(a: &BitVector, b: &BitVector) -> usize {
+    assert_eq!(
+        a.num_bits(),
+        b.num_bits(),
+        "BitVectors must have the same number of bits"
+    );
     a.iter()
         .zip(b.iter())
         .fold(0, |acc, (a, b)| acc + (a & b).count_ones()) as usize
 }
 
+/// Divide `num` by `den`, returning 0 instead of NaN when `den` is zero, as
+/// happens when comparing two empty fingerprints.
+fn ratio(num: f64, den: f64) -> f64 {
+    if den == 0.0 {
+        0.0
+    } else {
+        num / den
+    }
+}
+
+/// The bit counts used by the similarity metrics: the number of bits set in
+/// `a`, in `b`, and in both.
+fn counts(a: &BitVector, b: &BitVector) -> (f64, f64, f64) {
+    (a.count() as f64, b.count() as f64, intersect(a, b) as f64)
+}
+
+/// The Tanimoto similarity of fingerprints with popcounts `a` and `b` and
+/// `common` bits in common.
+fn tanimoto_counts(a: usize, b: usize, common: usize) -> f64 {
+    ratio(common as f64, (a + b - common) as f64)
+}
+
 /// Computes the Tanimoto distance between bit vectors a and b
 ///
 /// T(a, b) = (a ∩ b) / (a + b - a ∩ b), at least according to
 /// featurebase.com/blog/tanimoto-and-chemical-similarity-in-featurebase
+///
+/// Like the other similarity metrics, this returns 0 if both `a` and `b` are
+/// empty.
 pub fn tanimoto(a: &BitVector, b: &BitVector) -> f64 {
-    let num = intersect(a, b);
-    let den: usize = a.count() + b.count() - num;
-    num as f64 / den as f64
+    tanimoto_counts(a.count(), b.count(), intersect(a, b))
+}
+
+/// D(a, b) = 2(a ∩ b) / (a + b)
+pub fn dice(a: &BitVector, b: &BitVector) -> f64 {
+    let (a, b, c) = counts(a, b);
+    ratio(2.0 * c, a + b)
+}
+
+/// C(a, b) = (a ∩ b) / sqrt(ab)
+pub fn cosine(a: &BitVector, b: &BitVector) -> f64 {
+    let (a, b, c) = counts(a, b);
+    ratio(c, (a * b).sqrt())
+}
+
+/// The asymmetric Tversky index, weighting the bits unique to `a` by `alpha`
+/// and those unique to `b` by `beta`:
+///
+/// T(a, b) = (a ∩ b) / (α(a - a ∩ b) + β(b - a ∩ b) + a ∩ b)
+///
+/// With `alpha` = `beta` = 1 this is the Tanimoto similarity, and with both
+/// equal to 0.5 it is the Dice similarity.
+pub fn tversky(a: &BitVector, b: &BitVector, alpha: f64, beta: f64) -> f64 {
+    let (a, b, c) = counts(a, b);
+    ratio(c, alpha * (a - c) + beta * (b - c) + c)
+}
+
+/// S(a, b) = (a ∩ b) / (2a + 2b - 3(a ∩ b))
+pub fn sokal(a: &BitVector, b: &BitVector) -> f64 {
+    let (a, b, c) = counts(a, b);
+    ratio(c, 2.0 * a + 2.0 * b - 3.0 * c)
+}
+
+/// R(a, b) = (a ∩ b) / n, where n is the number of bits in the vectors.
+pub fn russel(a: &BitVector, b: &BitVector) -> f64 {
+    ratio(intersect(a, b) as f64, a.num_bits() as f64)
+}
+
+/// K(a, b) = (a ∩ b)(a + b) / 2ab
+pub fn kulczynski(a: &BitVector, b: &BitVector) -> f64 {
+    let (a, b, c) = counts(a, b);
+    ratio(c * (a + b), 2.0 * a * b)
+}
+
+/// M(a, b) = ((a ∩ b)(a + b) - ab) / ab, ranging from -1 to 1
+pub fn mc_connaughey(a: &BitVector, b: &BitVector) -> f64 {
+    let (a, b, c) = counts(a, b);
+    ratio(c * (a + b) - a * b, a * b)
+}
+
+/// B(a, b) = (a ∩ b) / max(a, b)
+pub fn braun_blanquet(a: &BitVector, b: &BitVector) -> f64 {
+    let (a, b, c) = counts(a, b);
+    ratio(c, a.max(b))
+}
+
+/// The total counts in `a`, in `b`, and in their intersection, taking the
+/// smaller count for features present in both.
+fn sparse_counts(
+    a: &HashMap<usize, usize>,
+    b: &HashMap<usize, usize>,
+) -> (f64, f64, f64) {
+    let common: usize = a
+        .iter()
+        .filter_map(|(k, &v)| b.get(k).map(|&w| v.min(w)))
+        .sum();
+    let a: usize = a.values().sum();
+    let b: usize = b.values().sum();
+    (a as f64, b as f64, common as f64)
+}
+
+/// The Tanimoto similarity between sparse count fingerprints like those from
+/// [ROMol::morgan_fingerprint], treating each count as a multiset.
+pub fn sparse_tanimoto(
+    a: &HashMap<usize, usize>,
+    b: &HashMap<usize, usize>,
+) -> f64 {
+    let (a, b, c) = sparse_counts(a, b);
+    ratio(c, a + b - c)
+}
+
+/// The Dice similarity between sparse count fingerprints, see
+/// [sparse_tanimoto].
+pub fn sparse_dice(
+    a: &HashMap<usize, usize>,
+    b: &HashMap<usize, usize>,
+) -> f64 {
+    let (a, b, c) = sparse_counts(a, b);
+    ratio(2.0 * c, a + b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::bv;
+
+    #[test]
+    fn substructure_screening() {
+        let query = ROMol::from_smiles("c1ccccc1O");
+        let mol = ROMol::from_smiles("c1ccccc1CCO");
+        let phenol = ROMol::from_smiles("Oc1ccc(C)cc1");
+        for fp in [
+            |m: &ROMol| pattern_fingerprint(m, 2048),
+            |m: &ROMol| layered_fingerprint(m, LayerFlags::ALL, 2048),
+        ] {
+            let q = fp(&query);
+            assert_eq!(intersect(&q, &fp(&phenol)), q.count());
+            assert!(intersect(&q, &fp(&mol)) < q.count());
+        }
+    }
+
+    #[test]
+    fn metrics() {
+        // a = 3, b = 2, c = 1, n = 71
+        let a = bv(&[0, 1, 2], 71);
+        let b = bv(&[2, 70], 71);
+        let close = |x: f64, y: f64| (x - y).abs() < 1e-12;
+        assert!(close(tanimoto(&a, &b), 1.0 / 4.0));
+        assert!(close(dice(&a, &b), 2.0 / 5.0));
+        assert!(close(cosine(&a, &b), 1.0 / 6f64.sqrt()));
+        assert!(close(tversky(&a, &b, 1.0, 1.0), tanimoto(&a, &b)));
+        assert!(close(tversky(&a, &b, 0.5, 0.5), dice(&a, &b)));
+        assert!(close(tversky(&a, &b, 0.9, 0.1), 1.0 / (1.8 + 0.1 + 1.0)));
+        assert!(close(sokal(&a, &b), 1.0 / (6.0 + 4.0 - 3.0)));
+        assert!(close(russel(&a, &b), 1.0 / 71.0));
+        assert!(close(kulczynski(&a, &b), 5.0 / 12.0));
+        assert!(close(mc_connaughey(&a, &b), (5.0 - 6.0) / 6.0));
+        assert!(close(braun_blanquet(&a, &b), 1.0 / 3.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "same number of bits")]
+    fn mismatched_sizes() {
+        tanimoto(&bv(&[0], 10), &bv(&[0], 71));
+    }
+
+    #[test]
+    fn empty() {
+        let a = bv(&[], 64);
+        assert_eq!(tanimoto(&a, &a), 0.0);
+        assert_eq!(dice(&a, &a), 0.0);
+        assert_eq!(cosine(&a, &a), 0.0);
+        assert_eq!(sparse_tanimoto(&HashMap::new(), &HashMap::new()), 0.0);
+    }
+
+    #[test]
+    fn sparse() {
+        let a = HashMap::from([(1, 2), (5, 1)]);
+        let b = HashMap::from([(1, 1), (7, 3)]);
+        // a = 3, b = 4, c = 1
+        assert_eq!(sparse_tanimoto(&a, &b), 1.0 / 6.0);
+        assert_eq!(sparse_dice(&a, &b), 2.0 / 7.0);
+        assert_eq!(sparse_tanimoto(&a, &a), 1.0);
+    }
+
+    #[test]
+    fn maccs() {
+        let fp = maccs_keys(&ROMol::from_smiles("c1ccccc1O"));
+        assert_eq!(fp.len(), MACCS_SIZE.div_ceil(64));
+        assert!(fp.count() > 0);
+    }
 }
@@ -0,0 +1,222 @@
+//! Reading and writing fingerprints in the chemfp FPS format.
+//!
+//! An FPS file is a text file with a `#FPS1` line, optional `#key=value`
+//! header lines, and then one fingerprint per line, written as hex followed by
+//! a tab and the fingerprint's identifier:
+//!
+//! ```text
+//! #FPS1
+//! #num_bits=2048
+//! #type=RDKit-Morgan/1 radius=2 fpSize=2048
+//! 0400000000...000000<TAB>aspirin
+//! ```
+//!
+//! The fingerprints are encoded with [BitVector::to_hex] and decoded with
+//! [BitVector::from_hex]. See
+//! <https://chemfp.readthedocs.io/en/latest/fps_format.html> for the details.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{bitvector::BitVector, RDError};
+
+/// The metadata in the header of an FPS file. Unrecognized header lines are
+/// ignored when reading.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FpsHeader {
+    /// the number of bits in each fingerprint. If this is missing, the
+    /// number is inferred from the length of the first fingerprint
+    pub num_bits: Option<usize>,
+    /// a description of the fingerprint type and its parameters
+    pub fp_type: Option<String>,
+    pub software: Option<String>,
+    /// the files the fingerprints were generated from
+    pub sources: Vec<String>,
+    pub date: Option<String>,
+}
+
+/// Read the header and every (id, fingerprint) pair from FPS data in `r`.
+/// Returns an error if the data cannot be read, does not start with the
+/// `#FPS1` line, or contains a fingerprint that is not valid hex of the
+/// expected length or has no identifier.
+pub fn read_fps(
+    r: impl BufRead,
+) -> Result<(FpsHeader, Vec<(String, BitVector)>), RDError> {
+    let mut header = FpsHeader::default();
+    let mut fps = Vec::new();
+    let mut lines = r.lines();
+    if lines.next().transpose()?.as_deref() != Some("#FPS1") {
+        eprintln!("missing #FPS1 line");
+        return Err(RDError);
+    }
+    // zero-based line indices, counting the #FPS1 line
+    for (i, line) in (1..).zip(lines) {
+        let line = line?;
+        if let Some(meta) = line.strip_prefix('#') {
+            if !fps.is_empty() {
+                eprintln!("header line {} after fingerprints", i + 1);
+                return Err(RDError);
+            }
+            let Some((key, value)) = meta.split_once('=') else {
+                continue;
+            };
+            match key {
+                "num_bits" => {
+                    let Ok(n) = value.trim().parse() else {
+                        eprintln!("invalid num_bits on line {}", i + 1);
+                        return Err(RDError);
+                    };
+                    header.num_bits = Some(n);
+                }
+                "type" => header.fp_type = Some(value.to_owned()),
+                "software" => header.software = Some(value.to_owned()),
+                "source" => header.sources.push(value.to_owned()),
+                "date" => header.date = Some(value.to_owned()),
+                _ => {}
+            }
+            continue;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        let mut fields = line.split('\t');
+        let hex = fields.next().unwrap_or_default();
+        let Some(id) = fields.next().filter(|id| !id.is_empty()) else {
+            eprintln!("missing identifier on line {}", i + 1);
+            return Err(RDError);
+        };
+        let nbits = *header.num_bits.get_or_insert(hex.len() * 4);
+        let Ok(fp) = BitVector::from_hex(hex, nbits) else {
+            eprintln!(
+                "invalid fingerprint on line {} for {nbits} bits: {hex}",
+                i + 1
+            );
+            return Err(RDError);
+        };
+        fps.push((id.to_owned(), fp));
+    }
+    Ok((header, fps))
+}
+
+/// Write `fps` to `w` in the FPS format. If [FpsHeader::num_bits] is not set,
+/// it is taken from the first fingerprint. Returns an error if any of the
+/// fingerprints have a different number of bits.
+pub fn write_fps<'a>(
+    mut w: impl Write,
+    header: &FpsHeader,
+    fps: impl IntoIterator<Item = (&'a str, &'a BitVector)>,
+) -> Result<(), RDError> {
+    let mut fps = fps.into_iter().peekable();
+    let num_bits = header
+        .num_bits
+        .or_else(|| fps.peek().map(|(_, fp)| fp.num_bits()));
+
+    writeln!(w, "#FPS1")?;
+    if let Some(n) = num_bits {
+        writeln!(w, "#num_bits={n}")?;
+    }
+    if let Some(t) = &header.fp_type {
+        writeln!(w, "#type={t}")?;
+    }
+    if let Some(s) = &header.software {
+        writeln!(w, "#software={s}")?;
+    }
+    for s in &header.sources {
+        writeln!(w, "#source={s}")?;
+    }
+    if let Some(d) = &header.date {
+        writeln!(w, "#date={d}")?;
+    }
+    for (id, fp) in fps {
+        if Some(fp.num_bits()) != num_bits {
+            eprintln!(
+                "fingerprint {id} has {} bits, expected {num_bits:?}",
+                fp.num_bits()
+            );
+            return Err(RDError);
+        }
+        writeln!(w, "{}\t{id}", fp.to_hex())?;
+    }
+    Ok(())
+}
+
+/// Read the FPS file at `path`, see [read_fps].
+pub fn read_fps_file(
+    path: impl AsRef<Path>,
+) -> Result<(FpsHeader, Vec<(String, BitVector)>), RDError> {
+    read_fps(BufReader::new(File::open(path)?))
+}
+
+/// Write an FPS file to `path`, see [write_fps].
+pub fn write_fps_file<'a>(
+    path: impl AsRef<Path>,
+    header: &FpsHeader,
+    fps: impl IntoIterator<Item = (&'a str, &'a BitVector)>,
+) -> Result<(), RDError> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write_fps(&mut w, header, fps)?;
+    w.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::bv;
+
+    #[test]
+    fn round_trip() {
+        let fps = [bv(&[1, 2, 3], 166), bv(&[], 166), bv(&[165], 166)];
+        let header = FpsHeader {
+            fp_type: Some("RDKit-MACCS166/2".to_owned()),
+            sources: vec!["a.sdf".to_owned(), "b.sdf".to_owned()],
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        let ids = ["a", "b", "c d"];
+        write_fps(&mut buf, &header, ids.into_iter().zip(&fps)).unwrap();
+
+        let text = String::from_utf8(buf.clone()).unwrap();
+        assert!(
+            text.starts_with("#FPS1\n#num_bits=166\n#type=RDKit-MACCS166/2")
+        );
+
+        let (got_header, got) = read_fps(buf.as_slice()).unwrap();
+        assert_eq!(got_header, FpsHeader { num_bits: Some(166), ..header });
+        assert_eq!(got.len(), 3);
+        for ((id, fp), (want_id, want)) in got.iter().zip(ids.iter().zip(&fps))
+        {
+            assert_eq!(id, want_id);
+            assert_eq!(fp, want);
+        }
+    }
+
+    #[test]
+    fn mismatched_bits() {
+        let fps = [bv(&[1], 8), bv(&[1], 16)];
+        let mut buf = Vec::new();
+        let res = write_fps(
+            &mut buf,
+            &FpsHeader::default(),
+            ["a", "b"].into_iter().zip(&fps),
+        );
+        assert!(res.is_err());
+        assert!(read_fps("#FPS1\n0102\tx\n01\ty\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(read_fps("#FPS1\n01\tx\n".as_bytes()).is_ok());
+        // missing the #FPS1 line
+        assert!(read_fps("01\tx\n".as_bytes()).is_err());
+        assert!(read_fps("#num_bits=8\n01\tx\n".as_bytes()).is_err());
+        assert!(read_fps("".as_bytes()).is_err());
+        // missing identifiers
+        assert!(read_fps("#FPS1\n01\n".as_bytes()).is_err());
+        assert!(read_fps("#FPS1\n01\t\n".as_bytes()).is_err());
+    }
+}
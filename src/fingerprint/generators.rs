@@ -0,0 +1,294 @@
+//! Path-based, atom-pair, and topological torsion fingerprint generators.
+//!
+//! Like [MorganGenerator][super::MorganGenerator], each generator holds a
+//! fixed set of options and can produce both folded bit vectors and unfolded
+//! sparse count fingerprints.
+
+use std::{collections::HashMap, ffi::c_uint};
+
+use crate::{bitvector::BitVector, RDError, ROMol};
+
+fn sparse_counts(
+    ptr: *mut rdkit_sys::MorganFingerprintElem,
+    len: usize,
+) -> HashMap<usize, usize> {
+    let elems = unsafe { Vec::from_raw_parts(ptr, len, len) };
+    elems
+        .into_iter()
+        .map(|e| (e.bit as usize, e.count as usize))
+        .collect()
+}
+
+/// The number of bits RDKit's count simulation uses for each feature.
+const COUNT_SIMULATION_BITS: usize = 4;
+
+/// Check that a folded fingerprint of `fp_size` bits can hold at least one
+/// feature.
+fn check_size(fp_size: usize, count_simulation: bool) -> Result<(), RDError> {
+    let min = if count_simulation { COUNT_SIMULATION_BITS } else { 1 };
+    if fp_size < min {
+        eprintln!("fingerprint size must be at least {min}, got {fp_size}");
+        return Err(RDError);
+    }
+    Ok(())
+}
+
+/// Run `f` to fill a buffer of `fp_size` bits with RDKit's folded
+/// fingerprint. The C wrapper returns false after printing RDKit's exception
+/// to stderr if generation fails.
+fn folded(
+    fp_size: usize,
+    count_simulation: bool,
+    f: impl FnOnce(*mut bool) -> bool,
+) -> Result<BitVector, RDError> {
+    check_size(fp_size, count_simulation)?;
+    let mut bits = vec![false; fp_size];
+    if !f(bits.as_mut_ptr()) {
+        return Err(RDError);
+    }
+    Ok(bits.as_slice().into())
+}
+
+/// Generates RDKit's topological fingerprints, which hash the linear and
+/// branched subgraphs of a molecule.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RDKitFPGenerator {
+    /// the minimum number of bonds in a path. defaults to 1
+    pub min_path: usize,
+    /// the maximum number of bonds in a path. defaults to 7
+    pub max_path: usize,
+    /// defaults to 2048
+    pub fp_size: usize,
+    /// include explicit hydrogens in the paths. defaults to true
+    pub use_hs: bool,
+    /// include branched subgraphs as well as linear paths. defaults to true
+    pub branched_paths: bool,
+    /// distinguish paths by their bond orders. defaults to true
+    pub use_bond_order: bool,
+    /// defaults to false
+    pub count_simulation: bool,
+    /// the number of bits set for each path. defaults to 2, as in RDKit
+    pub num_bits_per_feature: usize,
+}
+
+impl Default for RDKitFPGenerator {
+    fn default() -> Self {
+        Self {
+            min_path: 1,
+            max_path: 7,
+            fp_size: 2048,
+            use_hs: true,
+            branched_paths: true,
+            use_bond_order: true,
+            count_simulation: false,
+            num_bits_per_feature: 2,
+        }
+    }
+}
+
+impl RDKitFPGenerator {
+    pub fn sparse_count_fingerprint(
+        &self,
+        mol: &ROMol,
+    ) -> HashMap<usize, usize> {
+        let mut len = 0;
+        let ptr = unsafe {
+            rdkit_sys::RDKit_RDKitFPGenerator_getSparseCountFingerprint(
+                mol.0,
+                self.min_path as c_uint,
+                self.max_path as c_uint,
+                self.use_hs,
+                self.branched_paths,
+                self.use_bond_order,
+                &mut len,
+            )
+        };
+        sparse_counts(ptr, len)
+    }
+
+    /// The folded bit vector fingerprint of `mol`, as generated by RDKit.
+    /// Returns an error if [RDKitFPGenerator::fp_size] is 0, or less than 4
+    /// with count simulation.
+    pub fn fingerprint(&self, mol: &ROMol) -> Result<BitVector, RDError> {
+        folded(self.fp_size, self.count_simulation, |out| unsafe {
+            rdkit_sys::RDKit_RDKitFPGenerator_getFingerprint(
+                mol.0,
+                self.min_path as c_uint,
+                self.max_path as c_uint,
+                self.use_hs,
+                self.branched_paths,
+                self.use_bond_order,
+                self.count_simulation,
+                self.num_bits_per_feature as c_uint,
+                self.fp_size,
+                out,
+            )
+        })
+    }
+}
+
+/// Generates atom-pair fingerprints, which hash pairs of atoms along with the
+/// topological distance between them.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AtomPairGenerator {
+    /// the minimum distance between paired atoms in bonds. defaults to 1
+    pub min_distance: usize,
+    /// the maximum distance between paired atoms in bonds. defaults to 30
+    pub max_distance: usize,
+    /// defaults to 2048
+    pub fp_size: usize,
+    /// defaults to false
+    pub include_chirality: bool,
+    /// use topological distances rather than 3D distances from the first
+    /// conformer. defaults to true
+    pub use_2d: bool,
+    /// defaults to true, as in RDKit
+    pub count_simulation: bool,
+}
+
+impl Default for AtomPairGenerator {
+    fn default() -> Self {
+        Self {
+            min_distance: 1,
+            max_distance: 30,
+            fp_size: 2048,
+            include_chirality: false,
+            use_2d: true,
+            count_simulation: true,
+        }
+    }
+}
+
+impl AtomPairGenerator {
+    pub fn sparse_count_fingerprint(
+        &self,
+        mol: &ROMol,
+    ) -> HashMap<usize, usize> {
+        let mut len = 0;
+        let ptr = unsafe {
+            rdkit_sys::RDKit_AtomPairGenerator_getSparseCountFingerprint(
+                mol.0,
+                self.min_distance as c_uint,
+                self.max_distance as c_uint,
+                self.include_chirality,
+                self.use_2d,
+                &mut len,
+            )
+        };
+        sparse_counts(ptr, len)
+    }
+
+    /// The folded bit vector fingerprint of `mol`, as generated by RDKit.
+    /// Returns an error if [AtomPairGenerator::fp_size] is 0, or less than 4 with
+    /// count simulation.
+    pub fn fingerprint(&self, mol: &ROMol) -> Result<BitVector, RDError> {
+        folded(self.fp_size, self.count_simulation, |out| unsafe {
+            rdkit_sys::RDKit_AtomPairGenerator_getFingerprint(
+                mol.0,
+                self.min_distance as c_uint,
+                self.max_distance as c_uint,
+                self.include_chirality,
+                self.use_2d,
+                self.count_simulation,
+                self.fp_size,
+                out,
+            )
+        })
+    }
+}
+
+/// Generates topological torsion fingerprints, which hash linear paths of
+/// four (by default) atoms.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TopologicalTorsionGenerator {
+    /// the number of atoms in each path. defaults to 4
+    pub torsion_atom_count: usize,
+    /// defaults to 2048
+    pub fp_size: usize,
+    /// defaults to false
+    pub include_chirality: bool,
+    /// defaults to true, as in RDKit
+    pub count_simulation: bool,
+}
+
+impl Default for TopologicalTorsionGenerator {
+    fn default() -> Self {
+        Self {
+            torsion_atom_count: 4,
+            fp_size: 2048,
+            include_chirality: false,
+            count_simulation: true,
+        }
+    }
+}
+
+impl TopologicalTorsionGenerator {
+    pub fn sparse_count_fingerprint(
+        &self,
+        mol: &ROMol,
+    ) -> HashMap<usize, usize> {
+        let mut len = 0;
+        let ptr = unsafe {
+            rdkit_sys::RDKit_TopologicalTorsionGenerator_getSparseCountFingerprint(
+                mol.0,
+                self.torsion_atom_count as c_uint,
+                self.include_chirality,
+                &mut len,
+            )
+        };
+        sparse_counts(ptr, len)
+    }
+
+    /// The folded bit vector fingerprint of `mol`, as generated by RDKit.
+    /// Returns an error if [TopologicalTorsionGenerator::fp_size] is 0, or less than 4 with
+    /// count simulation.
+    pub fn fingerprint(&self, mol: &ROMol) -> Result<BitVector, RDError> {
+        folded(self.fp_size, self.count_simulation, |out| unsafe {
+            rdkit_sys::RDKit_TopologicalTorsionGenerator_getFingerprint(
+                mol.0,
+                self.torsion_atom_count as c_uint,
+                self.include_chirality,
+                self.count_simulation,
+                self.fp_size,
+                out,
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert!(check_size(0, false).is_err());
+        assert!(check_size(1, false).is_ok());
+        assert!(check_size(3, true).is_err());
+        assert!(check_size(4, true).is_ok());
+    }
+
+    #[test]
+    fn generators() {
+        let mol = ROMol::from_smiles("c1ccccc1CCO");
+        let rdkit = RDKitFPGenerator::default();
+        let counts = rdkit.sparse_count_fingerprint(&mol);
+        assert!(!counts.is_empty());
+        let fp = rdkit.fingerprint(&mol).unwrap();
+        assert!(fp.count() <= 2 * counts.len());
+
+        let ap = AtomPairGenerator::default();
+        // one pair for each of the 9 * 8 / 2 pairs of heavy atoms
+        let n: usize = ap.sparse_count_fingerprint(&mol).values().sum();
+        assert_eq!(n, 36);
+
+        let tt = TopologicalTorsionGenerator::default();
+        assert!(tt.fingerprint(&mol).unwrap().count() > 0);
+
+        let ap = AtomPairGenerator { fp_size: 2, ..ap };
+        assert!(ap.fingerprint(&mol).is_err());
+    }
+}
@@ -0,0 +1,542 @@
+//! A Tanimoto similarity index that prunes candidates by popcount.
+//!
+//! Fingerprints are stored in buckets by their popcount, with the words of
+//! each bucket in one contiguous array. Since the Tanimoto similarity of
+//! fingerprints with popcounts a and b can be at most min(a, b) / max(a, b)
+//! (Swamidass and Baldi, J. Chem. Inf. Model. 2007, 47, 952), a threshold
+//! search for a query with popcount q only needs to visit the buckets between
+//! ⌈tq⌉ and ⌊q/t⌋, and a top-k search can stop as soon as the bound for the
+//! remaining buckets falls below the k-th best similarity.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+    thread,
+};
+
+use super::tanimoto_counts;
+use crate::{bitvector::BitVector, RDError};
+
+/// The first bytes of a saved index, including the format version.
+const MAGIC: &[u8; 8] = b"RDFPIDX1";
+
+/// The best possible Tanimoto similarity between fingerprints with popcounts
+/// `a` and `b`.
+fn upper_bound(a: usize, b: usize) -> f64 {
+    if a == 0 && b == 0 {
+        return 0.0;
+    }
+    a.min(b) as f64 / a.max(b) as f64
+}
+
+/// The range of popcounts that can reach `threshold` with a query of
+/// popcount `q`.
+fn popcount_range(q: usize, threshold: f64) -> (usize, usize) {
+    if threshold <= 0.0 {
+        return (0, usize::MAX);
+    }
+    // allow a little slack for rounding in the products
+    let lo = (threshold * q as f64 - 1e-9).ceil().max(0.0) as usize;
+    let hi = (q as f64 / threshold + 1e-9).floor() as usize;
+    (lo, hi)
+}
+
+/// The fingerprints with one popcount, stored contiguously with a fixed
+/// stride of [FingerprintIndex::stride] words per fingerprint.
+#[derive(Default)]
+struct Bucket {
+    ids: Vec<usize>,
+    words: Vec<u64>,
+}
+
+impl Bucket {
+    /// The IDs and words of the fingerprints in rows `start..end`.
+    fn rows(
+        &self,
+        stride: usize,
+        start: usize,
+        end: usize,
+    ) -> impl Iterator<Item = (usize, &[u64])> {
+        (start..end).map(move |i| {
+            (self.ids[i], &self.words[i * stride..(i + 1) * stride])
+        })
+    }
+}
+
+/// The number of bits in both `query` and `words`.
+fn intersect_words(query: &BitVector, words: &[u64]) -> usize {
+    query
+        .iter()
+        .zip(words)
+        .fold(0, |acc, (a, b)| acc + (a & b).count_ones()) as usize
+}
+
+/// Resolve a requested thread count, where 0 means all available threads.
+fn thread_count(num_threads: usize) -> usize {
+    match num_threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+}
+
+/// Insert `(id, sim)` into `best`, which is sorted by decreasing similarity
+/// and then by ID, keeping at most `k` entries.
+fn push_best(best: &mut Vec<(usize, f64)>, k: usize, id: usize, sim: f64) {
+    let pos = best.partition_point(|&(i, s)| s > sim || (s == sim && i < id));
+    if pos < k {
+        best.insert(pos, (id, sim));
+        best.truncate(k);
+    }
+}
+
+/// An in-memory index of fingerprints for fast threshold and top-k Tanimoto
+/// searches. Each fingerprint is identified by the order in which it was
+/// inserted, and every fingerprint must have the same number of bits.
+#[derive(Default)]
+pub struct FingerprintIndex {
+    /// fingerprints grouped by popcount
+    buckets: Vec<Bucket>,
+    /// the number of bits in each fingerprint, set by the first insertion
+    num_bits: usize,
+    /// the number of words in each fingerprint
+    stride: usize,
+    len: usize,
+}
+
+impl FingerprintIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `fp` to the index, returning its ID. Panics if `fp` does not have
+    /// the same number of bits as the fingerprints already in the index.
+    pub fn insert(&mut self, fp: BitVector) -> usize {
+        if self.len == 0 {
+            self.num_bits = fp.num_bits();
+            self.stride = fp.len();
+        }
+        assert_eq!(
+            fp.num_bits(),
+            self.num_bits,
+            "fingerprint size differs from the rest of the index"
+        );
+        let count = fp.count();
+        if self.buckets.len() <= count {
+            self.buckets.resize_with(count + 1, Bucket::default);
+        }
+        let id = self.len;
+        let bucket = &mut self.buckets[count];
+        bucket.ids.push(id);
+        bucket.words.extend(fp.iter());
+        self.len += 1;
+        id
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Panics if `query` has a different number of bits than the
+    /// fingerprints in the index, like [FingerprintIndex::insert].
+    fn check_query(&self, query: &BitVector) {
+        assert_eq!(
+            query.num_bits(),
+            self.num_bits,
+            "query size differs from the fingerprints in the index"
+        );
+    }
+
+    /// Call `f` on every fingerprint in the buckets at `counts`, passing its
+    /// popcount, ID, and words. The rows of each bucket are split evenly
+    /// across `num_threads` threads, and the results of each thread are
+    /// returned in order.
+    fn scan<R, F>(&self, counts: &[usize], num_threads: usize, f: F) -> Vec<R>
+    where
+        R: Send,
+        F: Fn(&mut dyn Iterator<Item = (usize, usize, &[u64])>) -> R + Sync,
+    {
+        let n = thread_count(num_threads);
+        let rows = |t: usize| {
+            counts.iter().flat_map(move |&count| {
+                let bucket = &self.buckets[count];
+                let len = bucket.ids.len();
+                bucket
+                    .rows(self.stride, len * t / n, len * (t + 1) / n)
+                    .map(move |(id, words)| (count, id, words))
+            })
+        };
+        if n == 1 {
+            return vec![f(&mut rows(0))];
+        }
+        thread::scope(|s| {
+            let handles: Vec<_> = (0..n)
+                .map(|t| {
+                    let (f, rows) = (&f, &rows);
+                    s.spawn(move || f(&mut rows(t)))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("search thread panicked"))
+                .collect()
+        })
+    }
+
+    /// The IDs and similarities of every fingerprint with a Tanimoto
+    /// similarity to `query` of at least `threshold`, sorted by ID.
+    pub fn threshold_search(
+        &self,
+        query: &BitVector,
+        threshold: f64,
+    ) -> Vec<(usize, f64)> {
+        self.threshold_search_threaded(query, threshold, 1)
+    }
+
+    /// Like [FingerprintIndex::threshold_search], but with the candidate
+    /// fingerprints split across `num_threads` threads. A `num_threads` of 0
+    /// uses all available threads.
+    pub fn threshold_search_threaded(
+        &self,
+        query: &BitVector,
+        threshold: f64,
+        num_threads: usize,
+    ) -> Vec<(usize, f64)> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        self.check_query(query);
+        let q = query.count();
+        let (lo, hi) = popcount_range(q, threshold);
+        let hi = hi.min(self.buckets.len().saturating_sub(1));
+        let counts: Vec<_> = (lo..=hi).collect();
+        let mut ret: Vec<_> = self
+            .scan(&counts, num_threads, |rows| {
+                rows.filter_map(|(count, id, words)| {
+                    let common = intersect_words(query, words);
+                    let sim = tanimoto_counts(q, count, common);
+                    (sim >= threshold).then_some((id, sim))
+                })
+                .collect::<Vec<_>>()
+            })
+            .into_iter()
+            .flatten()
+            .collect();
+        ret.sort_by_key(|&(id, _)| id);
+        ret
+    }
+
+    /// The IDs and similarities of the `k` fingerprints most similar to
+    /// `query`, sorted by decreasing similarity and then by ID.
+    pub fn top_k(&self, query: &BitVector, k: usize) -> Vec<(usize, f64)> {
+        self.top_k_threaded(query, k, 1)
+    }
+
+    /// Like [FingerprintIndex::top_k], but with the candidate fingerprints
+    /// split across `num_threads` threads. A `num_threads` of 0 uses all
+    /// available threads.
+    pub fn top_k_threaded(
+        &self,
+        query: &BitVector,
+        k: usize,
+        num_threads: usize,
+    ) -> Vec<(usize, f64)> {
+        if k == 0 || self.is_empty() {
+            return Vec::new();
+        }
+        self.check_query(query);
+        // k may be far larger than the index
+        let k = k.min(self.len);
+        let q = query.count();
+        // visit buckets in order of decreasing upper bound
+        let mut order: Vec<_> = (0..self.buckets.len()).collect();
+        order.sort_by(|&a, &b| {
+            upper_bound(q, b)
+                .total_cmp(&upper_bound(q, a))
+                .then(a.cmp(&b))
+        });
+
+        // each thread keeps its own top k, which is a lower bound on the
+        // overall k-th best similarity and so can safely prune its buckets
+        let partial = self.scan(&order, num_threads, |rows| {
+            let mut best: Vec<(usize, f64)> = Vec::with_capacity(k + 1);
+            for (count, id, words) in rows {
+                if best.len() == k && upper_bound(q, count) < best[k - 1].1 {
+                    break;
+                }
+                let common = intersect_words(query, words);
+                push_best(&mut best, k, id, tanimoto_counts(q, count, common));
+            }
+            best
+        });
+        let mut best = Vec::with_capacity(k + 1);
+        for (id, sim) in partial.into_iter().flatten() {
+            push_best(&mut best, k, id, sim);
+        }
+        best
+    }
+
+    /// Run [FingerprintIndex::threshold_search] for each of `queries`, split
+    /// across `num_threads` threads. A `num_threads` of 0 uses all available
+    /// threads.
+    pub fn threshold_search_many(
+        &self,
+        queries: &[BitVector],
+        threshold: f64,
+        num_threads: usize,
+    ) -> Vec<Vec<(usize, f64)>> {
+        self.map_queries(queries, num_threads, |q| {
+            self.threshold_search(q, threshold)
+        })
+    }
+
+    /// Run [FingerprintIndex::top_k] for each of `queries`, split across
+    /// `num_threads` threads. A `num_threads` of 0 uses all available threads.
+    pub fn top_k_many(
+        &self,
+        queries: &[BitVector],
+        k: usize,
+        num_threads: usize,
+    ) -> Vec<Vec<(usize, f64)>> {
+        self.map_queries(queries, num_threads, |q| self.top_k(q, k))
+    }
+
+    fn map_queries<F>(
+        &self,
+        queries: &[BitVector],
+        num_threads: usize,
+        f: F,
+    ) -> Vec<Vec<(usize, f64)>>
+    where
+        F: Fn(&BitVector) -> Vec<(usize, f64)> + Sync,
+    {
+        let num_threads = thread_count(num_threads);
+        if queries.is_empty() {
+            return Vec::new();
+        }
+        let chunk_size = queries.len().div_ceil(num_threads);
+        thread::scope(|s| {
+            let handles: Vec<_> = queries
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let f = &f;
+                    s.spawn(move || chunk.iter().map(f).collect::<Vec<_>>())
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().expect("search thread panicked"))
+                .collect()
+        })
+    }
+
+    /// Write the index to `w`. See [FingerprintIndex::save].
+    pub fn write(&self, w: &mut impl Write) -> Result<(), RDError> {
+        w.write_all(MAGIC)?;
+        w.write_all(&(self.len as u64).to_le_bytes())?;
+        let mut rows: Vec<_> = self
+            .buckets
+            .iter()
+            .flat_map(|b| b.rows(self.stride, 0, b.ids.len()))
+            .collect();
+        rows.sort_by_key(|&(id, _)| id);
+        for (_, words) in rows {
+            w.write_all(&(self.num_bits as u64).to_le_bytes())?;
+            for word in words {
+                w.write_all(&word.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read an index written by [FingerprintIndex::write]. IDs are preserved.
+    pub fn read(r: &mut impl Read) -> Result<Self, RDError> {
+        fn read_u64(r: &mut impl Read) -> Result<u64, RDError> {
+            let mut buf = [0; 8];
+            r.read_exact(&mut buf)?;
+            Ok(u64::from_le_bytes(buf))
+        }
+
+        let mut magic = [0; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            eprintln!("not a fingerprint index file");
+            return Err(RDError);
+        }
+        let len = read_u64(r)?;
+        let mut ret = Self::new();
+        for _ in 0..len {
+            let nbits = read_u64(r)? as usize;
+            if !ret.is_empty() && nbits != ret.num_bits {
+                eprintln!("fingerprint sizes differ within index file");
+                return Err(RDError);
+            }
+            let words: Vec<_> = (0..nbits.div_ceil(64))
+                .map(|_| read_u64(r))
+                .collect::<Result<_, _>>()?;
+            // bits past the end would corrupt the popcounts
+            let used = nbits - 64 * words.len().saturating_sub(1);
+            let last = words.last().copied().unwrap_or(0);
+            if last.checked_shr(used as u32).unwrap_or(0) != 0 {
+                eprintln!("fingerprint has bits set past its end");
+                return Err(RDError);
+            }
+            ret.insert(BitVector::from_words(words, nbits));
+        }
+        Ok(ret)
+    }
+
+    /// Save the index to the file at `path`, in a simple little-endian binary
+    /// format: an 8-byte magic number, the number of fingerprints, and then
+    /// the number of bits and 64-bit words of each fingerprint in ID order.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), RDError> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.write(&mut w)?;
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Load an index saved with [FingerprintIndex::save].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RDError> {
+        Self::read(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+impl FromIterator<BitVector> for FingerprintIndex {
+    fn from_iter<T: IntoIterator<Item = BitVector>>(iter: T) -> Self {
+        let mut ret = Self::new();
+        for fp in iter {
+            ret.insert(fp);
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fingerprint::search, tests::bv};
+
+    /// deterministic pseudo-random fingerprints of 256 bits with varying
+    /// densities
+    fn random_fps(n: usize, seed: u64) -> Vec<BitVector> {
+        let mut state = seed;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        (0..n)
+            .map(|i| {
+                let density = 1 + i % 7;
+                let bits: Vec<_> =
+                    (0..256).map(|_| next() % 8 < density as u64).collect();
+                bits.as_slice().into()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn range() {
+        assert_eq!(popcount_range(10, 0.5), (5, 20));
+        assert_eq!(popcount_range(7, 0.7), (5, 10));
+        assert_eq!(popcount_range(10, 0.0), (0, usize::MAX));
+    }
+
+    #[test]
+    fn matches_linear_scan() {
+        let fps = random_fps(300, 42);
+        let index: FingerprintIndex = fps.iter().cloned().collect();
+        assert_eq!(index.len(), 300);
+        for query in random_fps(10, 7) {
+            for t in [0.0, 0.3, 0.6] {
+                let want = search::threshold_search(&query, &fps, t);
+                assert_eq!(index.threshold_search(&query, t), want);
+            }
+            let want = search::top_k(&query, &fps, 5);
+            assert_eq!(index.top_k(&query, 5), want);
+        }
+    }
+
+    #[test]
+    fn empty_index() {
+        let index = FingerprintIndex::new();
+        let query = bv(&[], 256);
+        assert!(index.threshold_search(&query, 0.0).is_empty());
+        assert!(index.threshold_search_threaded(&query, 0.5, 2).is_empty());
+        assert!(index.top_k(&query, 3).is_empty());
+    }
+
+    #[test]
+    fn huge_k() {
+        let fps = random_fps(20, 23);
+        let index: FingerprintIndex = fps.iter().cloned().collect();
+        let got = index.top_k_threaded(&fps[0], usize::MAX, 2);
+        assert_eq!(got, search::top_k(&fps[0], &fps, 20));
+    }
+
+    #[test]
+    #[should_panic(expected = "query size differs")]
+    fn wrong_query_size() {
+        let index: FingerprintIndex = random_fps(5, 1).into_iter().collect();
+        index.top_k(&bv(&[], 128), 1);
+    }
+
+    #[test]
+    fn threaded() {
+        let fps = random_fps(300, 13);
+        let index: FingerprintIndex = fps.iter().cloned().collect();
+        for query in random_fps(5, 17) {
+            for threads in [2, 3, 0] {
+                assert_eq!(
+                    index.threshold_search_threaded(&query, 0.3, threads),
+                    index.threshold_search(&query, 0.3)
+                );
+                assert_eq!(
+                    index.top_k_threaded(&query, 7, threads),
+                    search::top_k(&query, &fps, 7)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn many() {
+        let index: FingerprintIndex = random_fps(100, 3).into_iter().collect();
+        let queries = random_fps(9, 11);
+        let got = index.threshold_search_many(&queries, 0.4, 4);
+        assert_eq!(got.len(), 9);
+        for (q, hits) in queries.iter().zip(got) {
+            assert_eq!(hits, index.threshold_search(q, 0.4));
+        }
+        let got = index.top_k_many(&queries, 3, 0);
+        assert_eq!(got[8], index.top_k(&queries[8], 3));
+    }
+
+    #[test]
+    fn round_trip() {
+        let fps = random_fps(50, 5);
+        let index: FingerprintIndex = fps.iter().cloned().collect();
+        let mut buf = Vec::new();
+        index.write(&mut buf).unwrap();
+        let index = FingerprintIndex::read(&mut buf.as_slice()).unwrap();
+        assert_eq!(index.len(), 50);
+        let query = fps[17].clone();
+        assert_eq!(index.top_k(&query, 1), [(17, 1.0)]);
+
+        assert!(FingerprintIndex::read(&mut &b"garbage!"[..]).is_err());
+
+        // set a bit past the end of a 70-bit fingerprint
+        let mut buf = Vec::new();
+        let index: FingerprintIndex = [bv(&[], 70)].into_iter().collect();
+        index.write(&mut buf).unwrap();
+        let last = buf.len() - 1;
+        buf[last] = 0x80;
+        assert!(FingerprintIndex::read(&mut buf.as_slice()).is_err());
+    }
+}
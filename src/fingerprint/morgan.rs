@@ -0,0 +1,255 @@
+//! A configurable Morgan (ECFP/FCFP) fingerprint generator.
+
+use std::{collections::HashMap, ffi::c_uint};
+
+use crate::{bitvector::BitVector, RDError, ROMol};
+
+/// Maps each set bit of a fingerprint to the (center atom, radius) pairs of
+/// the atom environments that set it.
+pub type BitInfo = HashMap<usize, Vec<(usize, usize)>>;
+
+/// One circular atom environment found while generating a Morgan fingerprint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MorganEnvironment {
+    /// the unfolded 32-bit identifier of the environment
+    pub id: u32,
+    /// the index of the central atom
+    pub atom: usize,
+    /// the number of bonds from `atom` covered by the environment
+    pub radius: usize,
+}
+
+/// Generates Morgan fingerprints with a fixed set of options, mirroring
+/// RDKit's `MorganGenerator`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MorganGenerator {
+    /// defaults to 2, corresponding to ECFP4
+    pub radius: usize,
+    /// the number of bits in folded fingerprints. defaults to 2048
+    pub fp_size: usize,
+    /// defaults to false
+    pub include_chirality: bool,
+    /// use pharmacophoric feature invariants (FCFP) instead of the default
+    /// connectivity invariants (ECFP). defaults to false
+    pub use_features: bool,
+    /// defaults to true
+    pub use_bond_types: bool,
+    /// skip atoms whose invariant is zero as environment centers. defaults to
+    /// false
+    pub only_nonzero_invariants: bool,
+}
+
+impl Default for MorganGenerator {
+    fn default() -> Self {
+        Self {
+            radius: 2,
+            fp_size: 2048,
+            include_chirality: false,
+            use_features: false,
+            use_bond_types: true,
+            only_nonzero_invariants: false,
+        }
+    }
+}
+
+/// Fold `envs` into `fp_size` bits, returning the bits and the environments
+/// contributing to each of them.
+fn fold(envs: &[MorganEnvironment], fp_size: usize) -> (Vec<bool>, BitInfo) {
+    let mut bits = vec![false; fp_size];
+    let mut info = BitInfo::new();
+    for env in envs {
+        let bit = env.id as usize % fp_size;
+        bits[bit] = true;
+        info.entry(bit).or_default().push((env.atom, env.radius));
+    }
+    (bits, info)
+}
+
+fn fold_counts(
+    envs: &[MorganEnvironment],
+    fp_size: usize,
+) -> HashMap<usize, usize> {
+    let mut ret = HashMap::new();
+    for env in envs {
+        *ret.entry(env.id as usize % fp_size).or_default() += 1;
+    }
+    ret
+}
+
+fn sparse_counts(envs: &[MorganEnvironment]) -> HashMap<usize, usize> {
+    let mut ret = HashMap::new();
+    for env in envs {
+        *ret.entry(env.id as usize).or_default() += 1;
+    }
+    ret
+}
+
+impl MorganGenerator {
+    /// Returns an error if `fp_size` is 0.
+    pub fn new(radius: usize, fp_size: usize) -> Result<Self, RDError> {
+        let ret = Self { radius, fp_size, ..Default::default() };
+        ret.folded_size()?;
+        Ok(ret)
+    }
+
+    /// [MorganGenerator::fp_size], or an error if it is 0.
+    fn folded_size(&self) -> Result<usize, RDError> {
+        if self.fp_size == 0 {
+            eprintln!("Morgan fingerprint size must be positive");
+            return Err(RDError);
+        }
+        Ok(self.fp_size)
+    }
+
+    /// Returns every atom environment in `mol` up to [MorganGenerator::radius],
+    /// including duplicates.
+    ///
+    /// If `invariants` is provided, it replaces the ECFP or FCFP invariants,
+    /// like the `customAtomInvariants` argument in RDKit. The same applies to
+    /// the fingerprint methods. Panics if `invariants` does not have one entry
+    /// per atom.
+    pub fn environments(
+        &self,
+        mol: &ROMol,
+        invariants: Option<&[u32]>,
+    ) -> Vec<MorganEnvironment> {
+        let invariants = invariants.unwrap_or_default();
+        if !invariants.is_empty() {
+            assert_eq!(invariants.len(), mol.num_atoms());
+        }
+        unsafe {
+            let mut len = 0;
+            let ptr = rdkit_sys::RDKit_MorganGenerator_getEnvironments(
+                mol.0,
+                self.radius as c_uint,
+                self.include_chirality,
+                self.use_features,
+                self.use_bond_types,
+                self.only_nonzero_invariants,
+                invariants.as_ptr(),
+                invariants.len(),
+                &mut len,
+            );
+            let envs = Vec::from_raw_parts(ptr, len, len);
+            envs.into_iter()
+                .map(|e| MorganEnvironment {
+                    id: e.bit,
+                    atom: e.atom as usize,
+                    radius: e.radius as usize,
+                })
+                .collect()
+        }
+    }
+
+    /// The folded bit vector fingerprint of `mol`. This and the other folded
+    /// fingerprints return an error if [MorganGenerator::fp_size] is 0.
+    pub fn fingerprint(
+        &self,
+        mol: &ROMol,
+        invariants: Option<&[u32]>,
+    ) -> Result<BitVector, RDError> {
+        Ok(self.fingerprint_with_info(mol, invariants)?.0)
+    }
+
+    /// Like [MorganGenerator::fingerprint], but also returns the
+    /// environments responsible for each bit.
+    pub fn fingerprint_with_info(
+        &self,
+        mol: &ROMol,
+        invariants: Option<&[u32]>,
+    ) -> Result<(BitVector, BitInfo), RDError> {
+        let fp_size = self.folded_size()?;
+        let (bits, info) = fold(&self.environments(mol, invariants), fp_size);
+        Ok((bits.as_slice().into(), info))
+    }
+
+    /// The folded count fingerprint of `mol`, mapping each set bit to the
+    /// number of environments that set it.
+    pub fn count_fingerprint(
+        &self,
+        mol: &ROMol,
+        invariants: Option<&[u32]>,
+    ) -> Result<HashMap<usize, usize>, RDError> {
+        let fp_size = self.folded_size()?;
+        Ok(fold_counts(&self.environments(mol, invariants), fp_size))
+    }
+
+    /// The unfolded count fingerprint of `mol`, keyed by the full 32-bit
+    /// environment identifiers like [ROMol::morgan_fingerprint].
+    pub fn sparse_count_fingerprint(
+        &self,
+        mol: &ROMol,
+        invariants: Option<&[u32]>,
+    ) -> HashMap<usize, usize> {
+        sparse_counts(&self.environments(mol, invariants))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fold_info() {
+        let env = |id, atom, radius| MorganEnvironment { id, atom, radius };
+        let envs = [env(3, 0, 0), env(13, 1, 1), env(4, 2, 0)];
+        let (bits, info) = fold(&envs, 10);
+        let set: Vec<_> = (0..bits.len()).filter(|&i| bits[i]).collect();
+        assert_eq!(set, [3, 4]);
+        assert_eq!(info[&3], [(0, 0), (1, 1)]);
+        assert_eq!(info[&4], [(2, 0)]);
+    }
+
+    #[test]
+    fn empty_fp_size() {
+        assert!(MorganGenerator::new(2, 0).is_err());
+        assert_eq!(MorganGenerator::new(3, 1024).unwrap().radius, 3);
+
+        // the fields are public, so the folded fingerprints check again
+        let gen = MorganGenerator { fp_size: 0, ..Default::default() };
+        let mol = ROMol::from_smiles("C");
+        assert!(gen.fingerprint(&mol, None).is_err());
+        assert!(gen.count_fingerprint(&mol, None).is_err());
+    }
+
+    #[test]
+    fn ecfp_fcfp() {
+        let mol = ROMol::from_smiles("c1ccccc1CCO");
+        let ecfp = MorganGenerator::default();
+        let fcfp = MorganGenerator { use_features: true, ..Default::default() };
+        let (fp, info) = ecfp.fingerprint_with_info(&mol, None).unwrap();
+        assert_eq!(fp.count(), info.len());
+        for envs in info.values() {
+            for &(atom, radius) in envs {
+                assert!(atom < mol.num_atoms());
+                assert!(radius <= ecfp.radius);
+            }
+        }
+        assert!(
+            ecfp.sparse_count_fingerprint(&mol, None)
+                != fcfp.sparse_count_fingerprint(&mol, None)
+        );
+        assert_eq!(
+            ecfp.sparse_count_fingerprint(&mol, None),
+            mol.morgan_fingerprint(2)
+        );
+    }
+
+    #[test]
+    fn custom_invariants() {
+        let mol = ROMol::from_smiles("CCO");
+        let gen = MorganGenerator::default();
+        let fp = gen.sparse_count_fingerprint(&mol, Some(&[1, 1, 1]));
+        assert!(fp != gen.sparse_count_fingerprint(&mol, None));
+        // the radius 0 environments are identified by the invariants
+        let radius0: Vec<_> = gen
+            .environments(&mol, Some(&[1, 1, 1]))
+            .into_iter()
+            .filter(|e| e.radius == 0)
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(radius0, [1, 1, 1]);
+    }
+}
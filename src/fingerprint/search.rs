@@ -0,0 +1,223 @@
+//! Tanimoto similarity search over many fingerprints.
+//!
+//! The functions here compute the popcount of the query once and use
+//! word-level intersections, and [FingerprintDb] additionally stores the
+//! popcount of every fingerprint, so each comparison is a single pass over the
+//! words of two vectors.
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use super::{intersect, tanimoto_counts};
+use crate::bitvector::BitVector;
+
+/// A search result, ordered by similarity and then by lower index, so that
+/// ties are broken in favor of earlier fingerprints.
+#[derive(PartialEq)]
+struct Hit(f64, usize);
+
+impl Eq for Hit {}
+
+impl PartialOrd for Hit {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Hit {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0).then(other.1.cmp(&self.1))
+    }
+}
+
+/// Keep the `k` best of `sims`, sorted by decreasing similarity.
+fn top_k_of(
+    sims: impl Iterator<Item = (usize, f64)>,
+    k: usize,
+) -> Vec<(usize, f64)> {
+    if k == 0 {
+        return Vec::new();
+    }
+    // k may be far larger than the number of fingerprints
+    let cap = k
+        .saturating_add(1)
+        .min(sims.size_hint().0.saturating_add(1));
+    let mut heap = BinaryHeap::with_capacity(cap);
+    for (i, sim) in sims {
+        heap.push(Reverse(Hit(sim, i)));
+        if heap.len() > k {
+            heap.pop();
+        }
+    }
+    heap.into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(Hit(sim, i))| (i, sim))
+        .collect()
+}
+
+/// The Tanimoto similarity between `query` and each of `fps`.
+pub fn bulk_tanimoto(query: &BitVector, fps: &[BitVector]) -> Vec<f64> {
+    let q = query.count();
+    fps.iter()
+        .map(|fp| tanimoto_counts(q, fp.count(), intersect(query, fp)))
+        .collect()
+}
+
+/// The indices and similarities of the `k` fingerprints in `fps` most similar
+/// to `query`, sorted by decreasing similarity.
+pub fn top_k(
+    query: &BitVector,
+    fps: &[BitVector],
+    k: usize,
+) -> Vec<(usize, f64)> {
+    let q = query.count();
+    let sims = fps.iter().enumerate().map(|(i, fp)| {
+        (i, tanimoto_counts(q, fp.count(), intersect(query, fp)))
+    });
+    top_k_of(sims, k)
+}
+
+/// The indices and similarities of every fingerprint in `fps` with a
+/// similarity to `query` of at least `threshold`, in index order.
+pub fn threshold_search(
+    query: &BitVector,
+    fps: &[BitVector],
+    threshold: f64,
+) -> Vec<(usize, f64)> {
+    let q = query.count();
+    fps.iter()
+        .enumerate()
+        .map(|(i, fp)| {
+            (i, tanimoto_counts(q, fp.count(), intersect(query, fp)))
+        })
+        .filter(|&(_, sim)| sim >= threshold)
+        .collect()
+}
+
+/// A collection of fingerprints along with their precomputed popcounts.
+#[derive(Default)]
+pub struct FingerprintDb {
+    fps: Vec<BitVector>,
+    counts: Vec<usize>,
+}
+
+impl FingerprintDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `fp` to the end of the database, returning its index.
+    pub fn push(&mut self, fp: BitVector) -> usize {
+        self.counts.push(fp.count());
+        self.fps.push(fp);
+        self.fps.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.fps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fps.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&BitVector> {
+        self.fps.get(index)
+    }
+
+    pub fn fingerprints(&self) -> &[BitVector] {
+        &self.fps
+    }
+
+    fn similarities<'a>(
+        &'a self,
+        query: &'a BitVector,
+    ) -> impl Iterator<Item = (usize, f64)> + 'a {
+        let q = query.count();
+        self.fps.iter().zip(&self.counts).enumerate().map(
+            move |(i, (fp, &count))| {
+                (i, tanimoto_counts(q, count, intersect(query, fp)))
+            },
+        )
+    }
+
+    /// See [bulk_tanimoto].
+    pub fn bulk_tanimoto(&self, query: &BitVector) -> Vec<f64> {
+        self.similarities(query).map(|(_, sim)| sim).collect()
+    }
+
+    /// See [top_k].
+    pub fn top_k(&self, query: &BitVector, k: usize) -> Vec<(usize, f64)> {
+        top_k_of(self.similarities(query), k)
+    }
+
+    /// See [threshold_search].
+    pub fn threshold_search(
+        &self,
+        query: &BitVector,
+        threshold: f64,
+    ) -> Vec<(usize, f64)> {
+        self.similarities(query)
+            .filter(|&(_, sim)| sim >= threshold)
+            .collect()
+    }
+}
+
+impl FromIterator<BitVector> for FingerprintDb {
+    fn from_iter<T: IntoIterator<Item = BitVector>>(iter: T) -> Self {
+        let mut ret = Self::new();
+        for fp in iter {
+            ret.push(fp);
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fingerprint::tanimoto, tests::bv};
+
+    fn fps() -> Vec<BitVector> {
+        vec![
+            bv(&[0, 1, 2, 3], 128),
+            bv(&[0, 1, 100], 128),
+            bv(&[64, 65], 128),
+            bv(&[0, 1, 2], 128),
+            bv(&[0, 1, 100], 128),
+        ]
+    }
+
+    #[test]
+    fn bulk() {
+        let query = bv(&[0, 1, 2], 128);
+        let fps = fps();
+        let want: Vec<_> = fps.iter().map(|fp| tanimoto(&query, fp)).collect();
+        assert_eq!(bulk_tanimoto(&query, &fps), want);
+        let db: FingerprintDb = fps.into_iter().collect();
+        assert_eq!(db.bulk_tanimoto(&query), want);
+    }
+
+    #[test]
+    fn huge_k() {
+        let fps = fps();
+        let got = top_k(&bv(&[0, 1, 2], 128), &fps, usize::MAX);
+        assert_eq!(got.len(), fps.len());
+    }
+
+    #[test]
+    fn search() {
+        let query = bv(&[0, 1, 2], 128);
+        let fps = fps();
+        let want = vec![(3, 1.0), (0, 0.75), (1, 0.5)];
+        assert_eq!(top_k(&query, &fps, 3), want);
+        assert!(top_k(&query, &fps, 0).is_empty());
+        assert_eq!(top_k(&query, &fps, 10).len(), 5);
+
+        let want = vec![(0, 0.75), (1, 0.5), (3, 1.0), (4, 0.5)];
+        assert_eq!(threshold_search(&query, &fps, 0.5), want);
+
+        let db: FingerprintDb = fps.into_iter().collect();
+        assert_eq!(db.top_k(&query, 3), top_k(&query, db.fingerprints(), 3));
+        assert_eq!(db.threshold_search(&query, 0.5), want);
+    }
+}
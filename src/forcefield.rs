@@ -0,0 +1,311 @@
+//! Geometry optimization with RDKit's MMFF94 and UFF implementations.
+
+use std::{
+    ffi::{c_int, c_uint},
+    marker::PhantomData,
+};
+
+use crate::{RDError, ROMol};
+
+/// The outcome of optimizing a single conformer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OptimizeResult {
+    /// whether the minimizer converged within the allowed iterations
+    pub converged: bool,
+    /// the final energy in kcal/mol
+    pub energy: f64,
+}
+
+/// Convert the status returned by RDKit's optimize functions: 0 for converged,
+/// 1 if more iterations were needed, and -1 if the force field could not be
+/// set up.
+fn converged(status: c_int) -> Result<bool, RDError> {
+    match status {
+        0 => Ok(true),
+        1 => Ok(false),
+        _ => Err(RDError),
+    }
+}
+
+fn optimize_results(
+    ptr: *mut rdkit_sys::RDKit_OptimizeResult,
+    len: usize,
+) -> Result<Vec<OptimizeResult>, RDError> {
+    let res = unsafe { Vec::from_raw_parts(ptr, len, len) };
+    res.into_iter()
+        .map(|r| {
+            Ok(OptimizeResult {
+                converged: converged(r.status)?,
+                energy: r.energy,
+            })
+        })
+        .collect()
+}
+
+impl ROMol {
+    /// Optimize the conformer with ID `conf_id` using MMFF94, returning
+    /// whether the optimization converged within `max_iters` iterations.
+    /// Returns an error if MMFF parameters are missing for the molecule.
+    pub fn mmff_optimize(
+        &mut self,
+        conf_id: usize,
+        max_iters: usize,
+    ) -> Result<bool, RDError> {
+        converged(unsafe {
+            rdkit_sys::RDKit_MMFFOptimizeMolecule(
+                self.0,
+                conf_id as c_int,
+                max_iters as c_uint,
+            )
+        })
+    }
+
+    /// Like [ROMol::mmff_optimize], but using UFF.
+    pub fn uff_optimize(
+        &mut self,
+        conf_id: usize,
+        max_iters: usize,
+    ) -> Result<bool, RDError> {
+        converged(unsafe {
+            rdkit_sys::RDKit_UFFOptimizeMolecule(
+                self.0,
+                conf_id as c_int,
+                max_iters as c_uint,
+            )
+        })
+    }
+
+    /// Optimize every conformer using MMFF94, returning the result for each
+    /// conformer in order. `num_threads` has the same meaning as
+    /// [EmbedParameters::num_threads][crate::EmbedParameters::num_threads]:
+    /// 0 uses all available threads and a negative value -n uses all but n.
+    pub fn mmff_optimize_confs(
+        &mut self,
+        max_iters: usize,
+        num_threads: i32,
+    ) -> Result<Vec<OptimizeResult>, RDError> {
+        let mut len = 0;
+        let ptr = unsafe {
+            rdkit_sys::RDKit_MMFFOptimizeMoleculeConfs(
+                self.0,
+                num_threads as c_int,
+                max_iters as c_uint,
+                &mut len,
+            )
+        };
+        optimize_results(ptr, len)
+    }
+
+    /// Like [ROMol::mmff_optimize_confs], but using UFF.
+    pub fn uff_optimize_confs(
+        &mut self,
+        max_iters: usize,
+        num_threads: i32,
+    ) -> Result<Vec<OptimizeResult>, RDError> {
+        let mut len = 0;
+        let ptr = unsafe {
+            rdkit_sys::RDKit_UFFOptimizeMoleculeConfs(
+                self.0,
+                num_threads as c_int,
+                max_iters as c_uint,
+                &mut len,
+            )
+        };
+        optimize_results(ptr, len)
+    }
+}
+
+/// A force field set up for a single conformer of a molecule.
+///
+/// RDKit's force fields operate directly on the coordinates of the conformer
+/// they were built from, so a [MolecularForceField] mutably borrows its
+/// molecule, and [MolecularForceField::minimize] updates the conformer in
+/// place. It is distinct from the SMIRNOFF
+/// [ForceField][crate::labeling::offxml::ForceField], which only holds
+/// parameter definitions for labeling.
+pub struct MolecularForceField<'a> {
+    inner: *mut rdkit_sys::RDKit_ForceField,
+    num_points: usize,
+    _mol: PhantomData<&'a mut ROMol>,
+}
+
+impl<'a> MolecularForceField<'a> {
+    fn new(inner: *mut rdkit_sys::RDKit_ForceField) -> Result<Self, RDError> {
+        if inner.is_null() {
+            return Err(RDError);
+        }
+        let num_points =
+            unsafe { rdkit_sys::RDKit_ForceField_numPoints(inner) as usize };
+        Ok(Self { inner, num_points, _mol: PhantomData })
+    }
+
+    /// Set up an MMFF94 force field for the conformer of `mol` with ID
+    /// `conf_id`. Returns an error if MMFF parameters are missing.
+    pub fn mmff(mol: &'a mut ROMol, conf_id: usize) -> Result<Self, RDError> {
+        Self::new(unsafe {
+            rdkit_sys::RDKit_MMFFGetMoleculeForceField(mol.0, conf_id as c_int)
+        })
+    }
+
+    /// Set up a UFF force field for the conformer of `mol` with ID `conf_id`.
+    pub fn uff(mol: &'a mut ROMol, conf_id: usize) -> Result<Self, RDError> {
+        Self::new(unsafe {
+            rdkit_sys::RDKit_UFFGetMoleculeForceField(mol.0, conf_id as c_int)
+        })
+    }
+
+    /// The number of atoms in the force field.
+    pub fn num_points(&self) -> usize {
+        self.num_points
+    }
+
+    /// The energy in kcal/mol at the current coordinates of the conformer.
+    pub fn energy(&self) -> f64 {
+        unsafe { rdkit_sys::RDKit_ForceField_calcEnergy(self.inner) }
+    }
+
+    /// The energy in kcal/mol at `coords`, a flat slice of x, y, z
+    /// coordinates in Å for each atom. The conformer is not modified.
+    pub fn energy_at(&self, coords: &[f64]) -> f64 {
+        assert_eq!(coords.len(), 3 * self.num_points);
+        unsafe {
+            rdkit_sys::RDKit_ForceField_calcEnergyPos(
+                self.inner,
+                coords.as_ptr(),
+            )
+        }
+    }
+
+    /// The gradient of the energy in kcal/mol/Å at `coords`, laid out in the
+    /// same way as `coords`. See [MolecularForceField::energy_at].
+    pub fn gradient(&self, coords: &[f64]) -> Vec<f64> {
+        assert_eq!(coords.len(), 3 * self.num_points);
+        let mut grad = vec![0.0; coords.len()];
+        unsafe {
+            rdkit_sys::RDKit_ForceField_calcGrad(
+                self.inner,
+                coords.as_ptr(),
+                grad.as_mut_ptr(),
+            );
+        }
+        grad
+    }
+
+    /// Minimize the energy of the conformer, returning whether the
+    /// minimization converged within `max_iters` iterations.
+    pub fn minimize(&mut self, max_iters: usize) -> bool {
+        unsafe {
+            rdkit_sys::RDKit_ForceField_minimize(
+                self.inner,
+                max_iters as c_uint,
+            ) == 0
+        }
+    }
+
+    /// Restrain the distance between the two `atoms` to lie between `min` and
+    /// `max` Å with force constant `k`.
+    pub fn add_distance_constraint(
+        &mut self,
+        atoms: [usize; 2],
+        min: f64,
+        max: f64,
+        k: f64,
+    ) {
+        let [i, j] = atoms.map(|a| a as c_uint);
+        unsafe {
+            rdkit_sys::RDKit_ForceField_addDistanceConstraint(
+                self.inner, i, j, min, max, k,
+            );
+        }
+    }
+
+    /// Restrain the angle formed by `atoms` to lie between `min` and `max`
+    /// degrees with force constant `k`.
+    pub fn add_angle_constraint(
+        &mut self,
+        atoms: [usize; 3],
+        min: f64,
+        max: f64,
+        k: f64,
+    ) {
+        let [i, j, l] = atoms.map(|a| a as c_uint);
+        unsafe {
+            rdkit_sys::RDKit_ForceField_addAngleConstraint(
+                self.inner, i, j, l, min, max, k,
+            );
+        }
+    }
+
+    /// Restrain the dihedral formed by `atoms` to lie between `min` and `max`
+    /// degrees with force constant `k`.
+    pub fn add_torsion_constraint(
+        &mut self,
+        atoms: [usize; 4],
+        min: f64,
+        max: f64,
+        k: f64,
+    ) {
+        let [i, j, l, m] = atoms.map(|a| a as c_uint);
+        unsafe {
+            rdkit_sys::RDKit_ForceField_addTorsionConstraint(
+                self.inner, i, j, l, m, min, max, k,
+            );
+        }
+    }
+
+    /// Hold atom `i` fixed in place during minimization.
+    pub fn add_fixed_point(&mut self, i: usize) {
+        unsafe {
+            rdkit_sys::RDKit_ForceField_addFixedPoint(self.inner, i as c_uint);
+        }
+    }
+}
+
+impl Drop for MolecularForceField<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            rdkit_sys::RDKit_ForceField_delete(self.inner);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::embedded;
+
+    #[test]
+    fn optimize() {
+        let mut mol = embedded("CCO", 3);
+        assert!(mol.mmff_optimize(0, 200).unwrap());
+        assert!(mol.uff_optimize(1, 200).unwrap());
+        let res = mol.mmff_optimize_confs(200, 1).unwrap();
+        assert_eq!(res.len(), 3);
+        assert!(res.iter().all(|r| r.converged));
+    }
+
+    #[test]
+    fn constrained_torsion() {
+        let mut mol = embedded("CCCC", 1);
+        let mut ff = MolecularForceField::mmff(&mut mol, 0).unwrap();
+        assert_eq!(ff.num_points(), 14);
+        ff.add_torsion_constraint([0, 1, 2, 3], 60.0, 60.0, 1e4);
+        let before = ff.energy();
+        ff.minimize(500);
+        assert!(ff.energy() < before);
+    }
+
+    #[test]
+    fn gradient() {
+        let mut mol = embedded("CC", 1);
+        let coords: Vec<f64> = mol
+            .get_conformer(0)
+            .get_positions()
+            .into_iter()
+            .flat_map(|p| [p.x, p.y, p.z])
+            .collect();
+        let ff = MolecularForceField::uff(&mut mol, 0).unwrap();
+        assert!((ff.energy() - ff.energy_at(&coords)).abs() < 1e-8);
+        assert_eq!(ff.gradient(&coords).len(), coords.len());
+    }
+}
@@ -0,0 +1,183 @@
+//! Hashable molecular identities.
+//!
+//! [ROMol] itself compares and hashes by canonical SMILES, which recomputes
+//! the SMILES on every comparison. A [MolKey] instead captures a string
+//! identity for a molecule once, under some chosen notion of sameness
+//! described by [Identity], and is cheaper to use as a `HashMap` key or to
+//! store in a `HashSet` for deduplication.
+//!
+//! ```text
+//! let a = MolKey::new(&ROMol::from_smiles("OCC"), Identity::Smiles);
+//! let b = MolKey::new(&ROMol::from_smiles("CCO"), Identity::Smiles);
+//! assert_eq!(a, b);
+//! ```
+
+use std::fmt::Display;
+
+use crate::ROMol;
+
+/// The hash functions provided by RDKit's `MolHash`. The discriminants match
+/// the values of the C++ `RDKit::MolHash::HashFunction` enum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HashFunction {
+    AnonymousGraph = 1,
+    ElementGraph = 2,
+    CanonicalSmiles = 3,
+    MurckoScaffold = 4,
+    ExtendedMurcko = 5,
+    MolFormula = 6,
+    AtomBondCounts = 7,
+    DegreeVector = 8,
+    Mesomer = 9,
+    HetAtomTautomer = 10,
+    HetAtomProtomer = 11,
+    RedoxPair = 12,
+    Regioisomer = 13,
+    NetCharge = 14,
+    SmallWorldIndexBR = 15,
+    SmallWorldIndexBRL = 16,
+    ArthorSubstructureOrder = 17,
+    HetAtomTautomerv2 = 18,
+}
+
+/// Which layers of a [RegistrationLayers] to include in a registration hash,
+/// following the hash schemes of RDKit's `RegistrationHash` module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HashScheme {
+    /// Every layer, distinguishing stereoisomers and tautomers
+    AllLayers,
+    /// Ignore stereochemistry
+    StereoInsensitive,
+    /// Ignore the position of mobile hydrogens
+    TautomerInsensitive,
+}
+
+/// The layers making up a molecule's registration hash, modeled on RDKit's
+/// `RegistrationHash.GetMolLayers`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RegistrationLayers {
+    pub formula: String,
+    pub canonical_smiles: String,
+    pub no_stereo_smiles: String,
+    pub tautomer_hash: String,
+    pub no_stereo_tautomer_hash: String,
+}
+
+impl RegistrationLayers {
+    pub fn new(mol: &ROMol) -> Self {
+        let mut no_stereo = mol.clone();
+        no_stereo.remove_stereochemistry();
+        use HashFunction::*;
+        Self {
+            formula: mol.mol_hash(MolFormula),
+            canonical_smiles: mol.mol_hash(CanonicalSmiles),
+            no_stereo_smiles: no_stereo.mol_hash(CanonicalSmiles),
+            tautomer_hash: mol.mol_hash(HetAtomTautomerv2),
+            no_stereo_tautomer_hash: no_stereo.mol_hash(HetAtomTautomerv2),
+        }
+    }
+
+    /// Join the layers selected by `scheme` into a single string. Unlike
+    /// RDKit, this returns the layers themselves rather than their SHA-1
+    /// digest, so two molecules share a hash only if their layers are
+    /// identical.
+    pub fn hash(&self, scheme: HashScheme) -> String {
+        let layers: &[&str] = match scheme {
+            HashScheme::AllLayers => &[
+                &self.formula,
+                &self.canonical_smiles,
+                &self.no_stereo_smiles,
+                &self.tautomer_hash,
+                &self.no_stereo_tautomer_hash,
+            ],
+            HashScheme::StereoInsensitive => &[
+                &self.formula,
+                &self.no_stereo_smiles,
+                &self.no_stereo_tautomer_hash,
+            ],
+            HashScheme::TautomerInsensitive => &[
+                &self.formula,
+                &self.tautomer_hash,
+                &self.no_stereo_tautomer_hash,
+            ],
+        };
+        layers.join("|")
+    }
+}
+
+/// The notion of molecular identity used to build a [MolKey].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Identity {
+    /// Canonical SMILES, as returned by [ROMol::to_smiles]
+    #[default]
+    Smiles,
+    /// Standard InChIKey, as returned by [ROMol::to_inchi_key]
+    InchiKey,
+    /// Registration hash layers, see [RegistrationLayers]
+    RegistrationHash(HashScheme),
+}
+
+/// A hashable identity for a molecule. Two keys are only equal if they were
+/// built with the same [Identity].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MolKey {
+    identity: Identity,
+    key: String,
+}
+
+impl MolKey {
+    pub fn new(mol: &ROMol, identity: Identity) -> Self {
+        let key = match identity {
+            Identity::Smiles => mol.to_smiles(),
+            Identity::InchiKey => mol.to_inchi_key(),
+            Identity::RegistrationHash(scheme) => {
+                RegistrationLayers::new(mol).hash(scheme)
+            }
+        };
+        Self { identity, key }
+    }
+
+    pub fn identity(&self) -> Identity {
+        self.identity
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.key
+    }
+}
+
+impl Display for MolKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn smiles_key() {
+        let a = MolKey::new(&ROMol::from_smiles("OCC"), Identity::Smiles);
+        let b = MolKey::new(&ROMol::from_smiles("CCO"), Identity::Smiles);
+        let c = MolKey::new(&ROMol::from_smiles("CCO"), Identity::InchiKey);
+        assert_eq!(a, b);
+        assert_ne!(b, c);
+        let set: HashSet<_> = [a, b, c].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn tautomer_insensitive() {
+        let hydroxy = ROMol::from_smiles("Oc1ccccn1");
+        let pyridone = ROMol::from_smiles("O=c1cccc[nH]1");
+
+        let all = Identity::RegistrationHash(HashScheme::AllLayers);
+        assert_ne!(MolKey::new(&hydroxy, all), MolKey::new(&pyridone, all));
+
+        let taut = Identity::RegistrationHash(HashScheme::TautomerInsensitive);
+        assert_eq!(MolKey::new(&hydroxy, taut), MolKey::new(&pyridone, taut));
+    }
+}
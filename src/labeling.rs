@@ -0,0 +1,248 @@
+//! Assign SMIRNOFF-style force field parameters to molecules.
+//!
+//! This follows the semantics of the openff-toolkit's `label_molecules`: each
+//! [ParameterHandler] holds an ordered list of tagged SMIRKS patterns, every
+//! pattern is matched against the molecule, and matches are keyed by their
+//! tagged atoms in a canonical order so that, for example, the bond (i, j) is
+//! the same as (j, i). When more than one parameter matches the same key, the
+//! one appearing last in the handler wins.
+//!
+//! The molecule should be prepared the same way the toolkit would prepare it,
+//! which usually means calling [ROMol::openff_clean] first.
+
+use std::collections::BTreeMap;
+
+use crate::{RDError, ROMol, SmartsPattern};
+
+pub mod offxml;
+
+/// The kinds of valence and non-bonded terms that can be labeled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ParameterKind {
+    Bonds,
+    Angles,
+    ProperTorsions,
+    ImproperTorsions,
+    VdW,
+    LibraryCharges,
+}
+
+impl ParameterKind {
+    /// The number of tagged atoms a SMIRKS pattern must have for this kind of
+    /// parameter, or `None` if any number of tagged atoms is allowed.
+    pub fn num_atoms(&self) -> Option<usize> {
+        match self {
+            ParameterKind::Bonds => Some(2),
+            ParameterKind::Angles => Some(3),
+            ParameterKind::ProperTorsions => Some(4),
+            ParameterKind::ImproperTorsions => Some(4),
+            ParameterKind::VdW => Some(1),
+            ParameterKind::LibraryCharges => None,
+        }
+    }
+
+    /// Put `atoms` into the canonical order for this kind of parameter.
+    ///
+    /// Bonds, angles, and proper torsions are symmetric under reversal, so
+    /// they are reversed if the first atom has a larger index than the last.
+    /// Impropers keep the central (second) atom in place and sort the three
+    /// atoms bonded to it. Library charges assign a value to each tagged atom,
+    /// so their order is left alone.
+    pub fn canonicalize(&self, atoms: &[usize]) -> Vec<usize> {
+        match self {
+            ParameterKind::VdW | ParameterKind::LibraryCharges => {
+                atoms.to_vec()
+            }
+            ParameterKind::Bonds
+            | ParameterKind::Angles
+            | ParameterKind::ProperTorsions => {
+                if atoms.first() > atoms.last() {
+                    atoms.iter().rev().copied().collect()
+                } else {
+                    atoms.to_vec()
+                }
+            }
+            ParameterKind::ImproperTorsions => {
+                let mut outer = [atoms[0], atoms[2], atoms[3]];
+                outer.sort();
+                vec![outer[0], atoms[1], outer[1], outer[2]]
+            }
+        }
+    }
+}
+
+/// A single parameter, identified by its `id` and the SMIRKS pattern that
+/// decides where it applies.
+#[derive(Clone, Debug)]
+pub struct Parameter {
+    pub id: String,
+    pub smirks: SmartsPattern,
+}
+
+/// Identifies a parameter within a set of handlers, for example in the
+/// results of [parameter_usage].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ParameterId {
+    pub kind: ParameterKind,
+    pub id: String,
+}
+
+/// The result of labeling a molecule with a [ParameterHandler]: a map from
+/// canonically-ordered atom indices to the parameter assigned to them.
+pub type Labels<'a> = BTreeMap<Vec<usize>, &'a Parameter>;
+
+/// An ordered collection of parameters of a single [ParameterKind].
+#[derive(Clone, Debug)]
+pub struct ParameterHandler {
+    kind: ParameterKind,
+    parameters: Vec<Parameter>,
+}
+
+impl ParameterHandler {
+    pub fn new(kind: ParameterKind) -> Self {
+        Self { kind, parameters: Vec::new() }
+    }
+
+    pub fn kind(&self) -> ParameterKind {
+        self.kind
+    }
+
+    pub fn parameters(&self) -> &[Parameter] {
+        &self.parameters
+    }
+
+    /// Append a parameter to the end of the handler, giving it the highest
+    /// priority so far. Returns an error if `smirks` cannot be parsed or does
+    /// not have the number of tagged atoms required by the handler's kind.
+    pub fn push(
+        &mut self,
+        id: impl Into<String>,
+        smirks: &str,
+    ) -> Result<(), RDError> {
+        let smirks = SmartsPattern::new(smirks)?;
+        if let Some(want) = self.kind.num_atoms() {
+            if smirks.num_tagged() != want {
+                eprintln!(
+                    "{:?} parameter requires {want} tagged atoms, found {} in \
+                     {smirks}",
+                    self.kind,
+                    smirks.num_tagged(),
+                );
+                return Err(RDError);
+            }
+        }
+        self.parameters.push(Parameter { id: id.into(), smirks });
+        Ok(())
+    }
+
+    /// Assign parameters to every matching set of atoms in `mol`, with later
+    /// parameters overwriting earlier ones. Like the toolkit, this considers
+    /// every ordered match, so distinct angles and torsions in small rings
+    /// that share the same atoms are all labeled.
+    pub fn label(&self, mol: &ROMol) -> Labels<'_> {
+        let mut ret = BTreeMap::new();
+        for param in &self.parameters {
+            for atoms in param.smirks.all_tagged_matches(mol) {
+                ret.insert(self.kind.canonicalize(&atoms), param);
+            }
+        }
+        ret
+    }
+}
+
+/// Label `mol` with each of `handlers`, returning the labels for each handler
+/// keyed by its [ParameterKind].
+pub fn label_molecule<'a>(
+    mol: &ROMol,
+    handlers: &'a [ParameterHandler],
+) -> BTreeMap<ParameterKind, Labels<'a>> {
+    handlers.iter().map(|h| (h.kind, h.label(mol))).collect()
+}
+
+/// Count how many times each parameter in `handlers` is assigned across all
+/// of `mols`, which should already be prepared for labeling. Every parameter
+/// is included, so unused parameters have a count of zero.
+pub fn parameter_usage<'m>(
+    mols: impl IntoIterator<Item = &'m ROMol>,
+    handlers: &[ParameterHandler],
+) -> BTreeMap<ParameterId, usize> {
+    let mut ret: BTreeMap<_, _> = handlers
+        .iter()
+        .flat_map(|h| {
+            h.parameters
+                .iter()
+                .map(|p| (ParameterId { kind: h.kind, id: p.id.clone() }, 0))
+        })
+        .collect();
+    for mol in mols {
+        for h in handlers {
+            for param in h.label(mol).into_values() {
+                let key = ParameterId { kind: h.kind, id: param.id.clone() };
+                *ret.entry(key).or_default() += 1;
+            }
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonicalize() {
+        use ParameterKind::*;
+        assert_eq!(Bonds.canonicalize(&[3, 1]), [1, 3]);
+        assert_eq!(Angles.canonicalize(&[0, 4, 2]), [0, 4, 2]);
+        assert_eq!(ProperTorsions.canonicalize(&[5, 1, 2, 0]), [0, 2, 1, 5]);
+        assert_eq!(ImproperTorsions.canonicalize(&[7, 0, 2, 5]), [2, 0, 5, 7]);
+    }
+
+    #[test]
+    fn last_match_wins() {
+        let mut mol = ROMol::from_smiles("CCO");
+        mol.openff_clean();
+
+        let mut bonds = ParameterHandler::new(ParameterKind::Bonds);
+        bonds.push("b1", "[#6X4:1]-[#6X4:2]").unwrap();
+        bonds.push("b2", "[#6:1]-[#1:2]").unwrap();
+        bonds.push("b3", "[*:1]~[*:2]").unwrap();
+        bonds.push("b4", "[#6X4:1]-[#1:2]").unwrap();
+
+        let labels = bonds.label(&mol);
+        assert_eq!(labels.len(), 8);
+        assert_eq!(labels[&vec![0, 1]].id, "b3");
+        assert_eq!(labels[&vec![0, 3]].id, "b4");
+        assert_eq!(labels[&vec![2, 8]].id, "b3");
+    }
+
+    #[test]
+    fn small_rings() {
+        let mut cyclopropane = ROMol::from_smiles("C1CC1");
+        cyclopropane.openff_clean();
+        let mut angles = ParameterHandler::new(ParameterKind::Angles);
+        angles.push("a1", "[#6:1]-[#6:2]-[#6:3]").unwrap();
+        let labels = angles.label(&cyclopropane);
+        let want = [vec![0, 1, 2], vec![0, 2, 1], vec![1, 0, 2]];
+        assert_eq!(labels.keys().cloned().collect::<Vec<_>>(), want);
+
+        let mut cyclobutane = ROMol::from_smiles("C1CCC1");
+        cyclobutane.openff_clean();
+        let mut torsions = ParameterHandler::new(ParameterKind::ProperTorsions);
+        torsions.push("t1", "[#6:1]-[#6:2]-[#6:3]-[#6:4]").unwrap();
+        let labels = torsions.label(&cyclobutane);
+        let want = [
+            vec![0, 1, 2, 3],
+            vec![0, 3, 2, 1],
+            vec![1, 0, 3, 2],
+            vec![2, 1, 0, 3],
+        ];
+        assert_eq!(labels.keys().cloned().collect::<Vec<_>>(), want);
+    }
+
+    #[test]
+    fn wrong_tag_count() {
+        let mut angles = ParameterHandler::new(ParameterKind::Angles);
+        assert!(angles.push("a1", "[*:1]~[*:2]").is_err());
+    }
+}
@@ -0,0 +1,176 @@
+//! Read SMIRNOFF force fields from `.offxml` files.
+//!
+//! Only the parameter ids and SMIRKS patterns are retained, which is all that
+//! is needed to label molecules. Handlers that are not described by a
+//! [ParameterKind], like `Constraints` and `Electrostatics`, are skipped.
+
+use std::{collections::BTreeMap, fs::read_to_string, path::Path};
+
+use crate::{RDError, ROMol};
+
+use super::{
+    label_molecule, parameter_usage, Labels, ParameterHandler, ParameterId,
+    ParameterKind,
+};
+
+/// The tag names of the handler and its parameters in the offxml file.
+fn tags(kind: ParameterKind) -> (&'static str, &'static str) {
+    match kind {
+        ParameterKind::Bonds => ("Bonds", "Bond"),
+        ParameterKind::Angles => ("Angles", "Angle"),
+        ParameterKind::ProperTorsions => ("ProperTorsions", "Proper"),
+        ParameterKind::ImproperTorsions => ("ImproperTorsions", "Improper"),
+        ParameterKind::VdW => ("vdW", "Atom"),
+        ParameterKind::LibraryCharges => ("LibraryCharges", "LibraryCharge"),
+    }
+}
+
+const KINDS: [ParameterKind; 6] = [
+    ParameterKind::Bonds,
+    ParameterKind::Angles,
+    ParameterKind::ProperTorsions,
+    ParameterKind::ImproperTorsions,
+    ParameterKind::VdW,
+    ParameterKind::LibraryCharges,
+];
+
+/// The parameter handlers read from a SMIRNOFF force field.
+#[derive(Clone, Debug)]
+pub struct ForceField {
+    handlers: Vec<ParameterHandler>,
+}
+
+impl ForceField {
+    /// Load a force field from the offxml file at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, RDError> {
+        Self::parse(&read_to_string(path)?)
+    }
+
+    /// Parse a force field from the contents of an offxml file.
+    pub fn parse(xml: &str) -> Result<Self, RDError> {
+        let doc = roxmltree::Document::parse(xml)?;
+        let root = doc.root_element();
+        if !root.has_tag_name("SMIRNOFF") {
+            eprintln!(
+                "expected SMIRNOFF root element, found {}",
+                root.tag_name().name()
+            );
+            return Err(RDError);
+        }
+
+        let mut handlers = Vec::new();
+        for node in root.children().filter(|n| n.is_element()) {
+            let Some(kind) =
+                KINDS.into_iter().find(|&k| node.has_tag_name(tags(k).0))
+            else {
+                continue;
+            };
+            let mut handler = ParameterHandler::new(kind);
+            for param in
+                node.children().filter(|n| n.has_tag_name(tags(kind).1))
+            {
+                let (Some(id), Some(smirks)) =
+                    (param.attribute("id"), param.attribute("smirks"))
+                else {
+                    eprintln!(
+                        "{} parameter missing id or smirks",
+                        tags(kind).1
+                    );
+                    return Err(RDError);
+                };
+                handler.push(id, smirks)?;
+            }
+            handlers.push(handler);
+        }
+
+        Ok(Self { handlers })
+    }
+
+    pub fn handlers(&self) -> &[ParameterHandler] {
+        &self.handlers
+    }
+
+    /// Returns the handler for `kind`, if it was present in the force field.
+    pub fn handler(&self, kind: ParameterKind) -> Option<&ParameterHandler> {
+        self.handlers.iter().find(|h| h.kind() == kind)
+    }
+
+    /// Label `mol` with every handler in the force field. See
+    /// [label_molecule].
+    pub fn label(&self, mol: &ROMol) -> BTreeMap<ParameterKind, Labels<'_>> {
+        label_molecule(mol, &self.handlers)
+    }
+
+    /// Count how many times each parameter is assigned across `mols`. See
+    /// [parameter_usage].
+    pub fn parameter_usage<'m>(
+        &self,
+        mols: impl IntoIterator<Item = &'m ROMol>,
+    ) -> BTreeMap<ParameterId, usize> {
+        parameter_usage(mols, &self.handlers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SDMolSupplier;
+
+    #[test]
+    fn load_offxml() {
+        let ff = ForceField::from_file("testfiles/small.offxml").unwrap();
+        assert_eq!(ff.handlers().len(), 6);
+        let vdw = ff.handler(ParameterKind::VdW).unwrap();
+        let ids: Vec<_> = vdw.parameters().iter().map(|p| &p.id).collect();
+        assert_eq!(ids, ["n1", "n2", "n12", "n16", "n18"]);
+    }
+
+    #[test]
+    fn label_ethanol() {
+        let ff = ForceField::from_file("testfiles/small.offxml").unwrap();
+        let mut mol = ROMol::from_smiles("CCO");
+        mol.openff_clean();
+        let labels = ff.label(&mol);
+
+        let bonds = &labels[&ParameterKind::Bonds];
+        assert_eq!(bonds[&vec![0, 1]].id, "b1");
+        assert_eq!(bonds[&vec![1, 2]].id, "b12");
+        assert_eq!(bonds[&vec![2, 8]].id, "b88");
+
+        let vdw = &labels[&ParameterKind::VdW];
+        assert_eq!(vdw[&vec![3]].id, "n2");
+        assert_eq!(vdw[&vec![8]].id, "n12");
+
+        assert!(labels[&ParameterKind::LibraryCharges].is_empty());
+    }
+
+    #[test]
+    fn usage() {
+        let ff = ForceField::from_file("testfiles/small.offxml").unwrap();
+        let mols: Vec<_> = SDMolSupplier::new("testfiles/small.sdf")
+            .unwrap()
+            .map(|mol| {
+                let mut mol = mol.unwrap();
+                mol.openff_clean();
+                mol
+            })
+            .collect();
+        assert_eq!(mols.len(), 2);
+        let usage = ff.parameter_usage(&mols);
+
+        let count =
+            |kind, id: &str| usage[&ParameterId { kind, id: id.to_owned() }];
+        use ParameterKind::*;
+        assert_eq!(count(Bonds, "b1"), 2);
+        assert_eq!(count(Bonds, "b83"), 11);
+        assert_eq!(count(Bonds, "b88"), 1);
+        assert_eq!(count(Angles, "a1"), 14);
+        assert_eq!(count(Angles, "a2"), 10);
+        assert_eq!(count(ProperTorsions, "t1"), 18);
+        assert_eq!(count(ProperTorsions, "t85"), 1);
+        assert_eq!(count(VdW, "n1"), 0);
+        assert_eq!(count(VdW, "n2"), 11);
+        assert_eq!(count(LibraryCharges, "TIP3P"), 0);
+        assert_eq!(usage.len(), 17);
+    }
+}
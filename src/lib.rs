@@ -1,7 +1,8 @@
 use std::{
     collections::HashMap,
-    ffi::{c_int, c_uint, CStr, CString},
+    ffi::{c_int, c_uint, CString},
     fmt::Display,
+    hash::Hash,
 };
 
 use bitflags::bitflags;
@@ -15,15 +16,37 @@ use self::bitvector::BitVector;
 #[cfg(test)]
 mod tests;
 
+pub mod alignment;
 pub mod bitvector;
+pub mod charges;
+pub mod cluster;
+pub mod conformer;
+pub mod descriptors;
+pub mod embed;
 pub mod errors;
+pub mod filter_catalog;
 pub mod fingerprint;
+pub mod forcefield;
 pub mod fragment;
+pub mod identity;
+pub mod labeling;
 pub mod mol_supplier;
+pub mod pickle;
+pub mod query;
+pub mod rules;
+#[cfg(feature = "serde")]
 pub mod rust_mol;
+#[cfg(feature = "serde")]
+pub mod serialize;
+pub mod tfd;
+pub mod torsions;
 
+pub use conformer::{Conformer, ConformerMut};
+pub use embed::EmbedParameters;
 pub use errors::RDError;
+pub use identity::{Identity, MolKey};
 pub use mol_supplier::SDMolSupplier;
+pub use query::SmartsPattern;
 pub use rdkit_sys::Point3D;
 
 pub struct SmilesParserParams {
@@ -41,23 +64,23 @@ impl Default for SmilesParserParams {
 
 pub struct ROMol(*mut RDKit_ROMol);
 
-pub struct Conformer(*mut rdkit_sys::RDKit_Conformer);
-
-impl Drop for Conformer {
-    fn drop(&mut self) {
-        unsafe {
-            rdkit_sys::RDKit_Conformer_delete(self.0);
-        }
-    }
+/// A bond between two atoms in an [ROMol].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bond {
+    pub begin: usize,
+    pub end: usize,
+    /// the bond order, with 1.5 for aromatic bonds
+    pub order: f64,
+    pub in_ring: bool,
 }
 
-impl Conformer {
-    pub fn get_positions(&self) -> Vec<Point3D> {
-        unsafe {
-            let mut n = 0;
-            let ps = rdkit_sys::RDKit_Conformer_getPositions(self.0, &mut n);
-            assert!(!ps.is_null());
-            Vec::from_raw_parts(ps, n as usize, n as usize)
+impl Bond {
+    /// Given one of the atoms in the bond, return the other one.
+    pub fn other(&self, atom: usize) -> usize {
+        if atom == self.begin {
+            self.end
+        } else {
+            self.begin
         }
     }
 }
@@ -89,6 +112,22 @@ impl ROMol {
         unsafe { Self(RDKit_SmartsToMol(s.as_ptr())) }
     }
 
+    /// Take ownership of a molecule returned by the C wrapper, which returns
+    /// null after printing RDKit's exception to stderr if it fails.
+    pub(crate) fn from_raw(ptr: *mut RDKit_ROMol) -> Result<Self, RDError> {
+        if ptr.is_null() {
+            return Err(RDError);
+        }
+        Ok(Self(ptr))
+    }
+
+    /// Like [ROMol::from_smarts], but returns an error instead of a null
+    /// molecule if RDKit fails to parse `smarts`.
+    pub fn try_from_smarts(smarts: &str) -> Result<Self, RDError> {
+        let s = CString::new(smarts).expect("failed to create CString");
+        Self::from_raw(unsafe { RDKit_SmartsToMol(s.as_ptr()) })
+    }
+
     /// Create an [ROMol] from a JSON string. The format can be either
     /// [CommonChem](https://github.com/CommonChem/CommonChem), or the RDKit
     /// [extension](http://rdkit.org/docs/source/rdkit.Chem.rdMolInterchange.html).
@@ -97,31 +136,51 @@ impl ROMol {
         unsafe { Self(RDKit_JSONToMol(s.as_ptr())) }
     }
 
+    /// Like [ROMol::from_json], but returns an error instead of a null
+    /// molecule if RDKit fails to parse `json`.
+    pub fn try_from_json(json: &str) -> Result<Self, RDError> {
+        let s = CString::new(json).expect("failed to create CString");
+        Self::from_raw(unsafe { RDKit_JSONToMol(s.as_ptr()) })
+    }
+
     /// Convert the molecule to RDKit's extension to the commonchem JSON format.
     pub fn to_json(&self) -> String {
         unsafe {
             let json = RDKit_MolToJSON(self.0);
-            let s = CStr::from_ptr(json);
-            s.to_str().unwrap().to_owned()
+            CString::from_raw(json).to_str().unwrap().to_owned()
         }
     }
 
     pub fn to_smiles(&self) -> String {
         unsafe {
             let smiles = RDKit_MolToSmiles(self.0);
-            let s = CStr::from_ptr(smiles);
-            s.to_str().unwrap().to_owned()
+            CString::from_raw(smiles).to_str().unwrap().to_owned()
         }
     }
 
     pub fn to_inchi_key(&self) -> String {
         unsafe {
             let smiles = rdkit_sys::RDKit_MolToInchiKey(self.0);
-            let s = CStr::from_ptr(smiles);
-            s.to_str().unwrap().to_owned()
+            CString::from_raw(smiles).to_str().unwrap().to_owned()
         }
     }
 
+    /// Compute one of RDKit's `MolHash` hashes of `self`. The hash is computed
+    /// on a copy, so `self` is left unchanged.
+    pub fn mol_hash(&self, func: identity::HashFunction) -> String {
+        let tmp = self.clone();
+        unsafe {
+            let hash = rdkit_sys::RDKit_MolHash(tmp.0, func as c_uint);
+            CString::from_raw(hash).to_str().unwrap().to_owned()
+        }
+    }
+
+    /// Build a hashable key for `self` using the notion of identity in
+    /// `identity`.
+    pub fn key(&self, identity: Identity) -> MolKey {
+        MolKey::new(self, identity)
+    }
+
     pub fn num_atoms(&self) -> usize {
         unsafe { rdkit_sys::RDKit_ROMol_getNumAtoms(self.0) as usize }
     }
@@ -135,6 +194,71 @@ impl ROMol {
         }
     }
 
+    pub fn bonds(&self) -> Vec<Bond> {
+        unsafe {
+            let mut nbonds = 0;
+            let ptr = rdkit_sys::RDKit_ROMol_getBonds(self.0, &mut nbonds);
+            let ret = Vec::from_raw_parts(ptr, nbonds, nbonds);
+            ret.into_iter()
+                .map(|b| Bond {
+                    begin: b.begin as usize,
+                    end: b.end as usize,
+                    order: b.order,
+                    in_ring: b.in_ring,
+                })
+                .collect()
+        }
+    }
+
+    /// Returns the atom map number of each atom, with 0 for unmapped atoms.
+    /// For a molecule parsed from SMIRKS, these are the tagged atom indices.
+    pub fn atom_map_numbers(&self) -> Vec<usize> {
+        unsafe {
+            let mut natoms = 0;
+            let ptr =
+                rdkit_sys::RDKit_ROMol_getAtomMapNumbers(self.0, &mut natoms);
+            let ret = Vec::from_raw_parts(ptr, natoms, natoms);
+            ret.into_iter().map(|i| i as usize).collect()
+        }
+    }
+
+    /// Returns the canonical rank of each atom, as computed by RDKit's
+    /// `CanonicalRankAtoms`. With `break_ties`, every atom receives a unique
+    /// rank, giving a canonical atom ordering. Without it, topologically
+    /// equivalent atoms share the same rank.
+    pub fn canonical_ranks(&self, break_ties: bool) -> Vec<usize> {
+        unsafe {
+            let mut natoms = 0;
+            let ptr = rdkit_sys::RDKit_CanonicalRankAtoms(
+                self.0,
+                break_ties,
+                &mut natoms,
+            );
+            let ret = Vec::from_raw_parts(ptr, natoms, natoms);
+            ret.into_iter().map(|i| i as usize).collect()
+        }
+    }
+
+    /// Returns the symmetry class of each atom. Atoms with the same class are
+    /// topologically equivalent. This is the same as
+    /// [canonical_ranks][ROMol::canonical_ranks] without tie breaking.
+    pub fn symmetry_classes(&self) -> Vec<usize> {
+        self.canonical_ranks(false)
+    }
+
+    /// Returns the groups of topologically equivalent atoms in `self`, each
+    /// sorted by atom index and ordered by their lowest index. Atoms with no
+    /// symmetry partner are returned in a group of their own.
+    pub fn equivalent_atoms(&self) -> Vec<Vec<usize>> {
+        let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (atom, class) in self.symmetry_classes().into_iter().enumerate() {
+            groups.entry(class).or_default().push(atom);
+        }
+        let mut ret: Vec<_> = groups.into_values().collect();
+        ret.sort();
+        ret
+    }
+
     pub fn sanitize(&mut self, ops: SanitizeFlags) {
         unsafe {
             let status = rdkit_sys::RDKit_SanitizeMol(self.0, ops.bits());
@@ -156,6 +280,13 @@ impl ROMol {
         }
     }
 
+    /// Remove all stereochemical information from atoms and bonds.
+    pub fn remove_stereochemistry(&mut self) {
+        unsafe {
+            rdkit_sys::RDKit_RemoveStereochemistry(self.0);
+        }
+    }
+
     pub fn add_hs(&mut self) {
         unsafe {
             rdkit_sys::RDKit_AddHs(self.0);
@@ -251,20 +382,18 @@ impl ROMol {
         }
     }
 
-    pub fn compute_2d_coords(&self) -> usize {
+    /// Compute 2D coordinates for `self` and return the ID of the new
+    /// conformer. RDKit clears any existing conformers first, which would
+    /// invalidate any outstanding [Conformer]s, so this takes `&mut self`.
+    pub fn compute_2d_coords(&mut self) -> usize {
         unsafe { rdkit_sys::RDKit_compute2DCoords(self.0) as usize }
     }
 
-    pub fn get_conformer(&self, id: usize) -> Conformer {
-        unsafe {
-            Conformer(rdkit_sys::RDKit_ROMol_getConformer(self.0, id as c_int))
-        }
-    }
-
     /// Call the sequence of operations to generate and return a vector of 2D
     /// coordinates
-    pub fn get_2d_coords(&self) -> Vec<Point3D> {
-        self.get_conformer(self.compute_2d_coords()).get_positions()
+    pub fn get_2d_coords(&mut self) -> Vec<Point3D> {
+        let id = self.compute_2d_coords();
+        self.get_conformer(id).get_positions()
     }
 }
 
@@ -274,6 +403,22 @@ impl Clone for ROMol {
     }
 }
 
+/// Molecules are compared by their canonical SMILES. See [MolKey] for other
+/// notions of identity.
+impl PartialEq for ROMol {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_smiles() == other.to_smiles()
+    }
+}
+
+impl Eq for ROMol {}
+
+impl Hash for ROMol {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.to_smiles().hash(state);
+    }
+}
+
 impl Drop for ROMol {
     fn drop(&mut self) {
         unsafe {
@@ -325,6 +470,10 @@ pub fn find_smarts_matches(mol: &ROMol, smarts: &str) -> Vec<Vec<usize>> {
             &mut match_size,
         );
         let matches = Vec::from_raw_parts(matches, len, len);
+        // an empty query has no atoms to chunk by
+        if match_size == 0 {
+            return Vec::new();
+        }
 
         let mut ret = Vec::new();
         for mat in matches.chunks(match_size) {
@@ -335,7 +484,8 @@ pub fn find_smarts_matches(mol: &ROMol, smarts: &str) -> Vec<Vec<usize>> {
 }
 
 /// returns the sequence of chemical environment "tuples" that match `smarts` in
-/// `mol`.
+/// `mol`. If the same pattern is going to be matched against many molecules,
+/// prefer compiling it once into a [SmartsPattern].
 pub fn find_smarts_matches_mol(mol: &ROMol, smarts: &ROMol) -> Vec<Vec<usize>> {
     let mut len = 0;
     let mut match_size = 0;
@@ -347,6 +497,10 @@ pub fn find_smarts_matches_mol(mol: &ROMol, smarts: &ROMol) -> Vec<Vec<usize>> {
             &mut match_size,
         );
         let matches = Vec::from_raw_parts(matches, len, len);
+        // an empty query has no atoms to chunk by
+        if match_size == 0 {
+            return Vec::new();
+        }
 
         let mut ret = Vec::new();
         for mat in matches.chunks(match_size) {
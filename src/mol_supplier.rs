@@ -48,12 +48,6 @@ impl Iterator for SDMolSupplier {
         if self.at_end() {
             return None;
         }
-        unsafe {
-            let mol = RDKit_mol_supplier_next(self.0);
-            if mol.is_null() {
-                return Some(Err(RDError));
-            }
-            Some(Ok(ROMol(mol)))
-        }
+        Some(ROMol::from_raw(unsafe { RDKit_mol_supplier_next(self.0) }))
     }
 }
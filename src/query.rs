@@ -0,0 +1,182 @@
+//! Precompiled SMARTS queries.
+//!
+//! [find_smarts_matches][crate::find_smarts_matches] has to parse its SMARTS
+//! pattern on every call, which adds up quickly when the same patterns are
+//! applied to many molecules. A [SmartsPattern] is parsed once and can then be
+//! matched against any number of [ROMol]s, including from multiple threads:
+//!
+//! ```text
+//! static AMIDE: LazyLock<SmartsPattern> =
+//!     LazyLock::new(|| SmartsPattern::new("[#6:1](=[#8:2])-[#7:3]").unwrap());
+//!
+//! let matches = AMIDE.matches(&mol);
+//! ```
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::{find_smarts_matches_mol, RDError, ROMol};
+
+/// A SMARTS pattern that has already been parsed into a query molecule.
+#[derive(Clone)]
+pub struct SmartsPattern {
+    smarts: String,
+    mol: ROMol,
+    /// indices of the tagged atoms in the pattern, ordered by map number
+    tagged: Vec<usize>,
+}
+
+impl SmartsPattern {
+    /// Parse `smarts` into a reusable query. Returns an error if RDKit fails
+    /// to parse the pattern.
+    pub fn new(smarts: &str) -> Result<Self, RDError> {
+        let mol = ROMol::try_from_smarts(smarts)?;
+        let mut tagged: Vec<_> = mol
+            .atom_map_numbers()
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, map)| map > 0)
+            .map(|(idx, map)| (map, idx))
+            .collect();
+        tagged.sort();
+        let tagged = tagged.into_iter().map(|(_, idx)| idx).collect();
+        Ok(Self { smarts: smarts.to_owned(), mol, tagged })
+    }
+
+    /// The SMARTS string this pattern was parsed from.
+    pub fn smarts(&self) -> &str {
+        &self.smarts
+    }
+
+    /// The underlying query molecule.
+    pub fn as_mol(&self) -> &ROMol {
+        &self.mol
+    }
+
+    /// Returns the sequence of atom index tuples in `mol` that match `self`.
+    /// The order within each tuple follows the atom order of the pattern.
+    pub fn matches(&self, mol: &ROMol) -> Vec<Vec<usize>> {
+        find_smarts_matches_mol(mol, &self.mol)
+    }
+
+    /// Like [SmartsPattern::matches], but without RDKit's uniquification
+    /// (`uniquify=false`), so every ordered match is returned, even when
+    /// several cover the same set of atoms. For example, the angle pattern
+    /// `[#6:1]-[#6:2]-[#6:3]` matches cyclopropane six times instead of once.
+    pub fn all_matches(&self, mol: &ROMol) -> Vec<Vec<usize>> {
+        let mut len = 0;
+        let mut match_size = 0;
+        unsafe {
+            let matches = rdkit_sys::RDKit_SubstructMatches(
+                mol.0,
+                self.mol.0,
+                false,
+                &mut len,
+                &mut match_size,
+            );
+            let matches = Vec::from_raw_parts(matches, len, len);
+            if match_size == 0 {
+                return Vec::new();
+            }
+            matches
+                .chunks(match_size)
+                .map(|m| m.iter().map(|&x| x as usize).collect())
+                .collect()
+        }
+    }
+
+    /// The number of tagged (atom-mapped) atoms in the pattern.
+    pub fn num_tagged(&self) -> usize {
+        self.tagged.len()
+    }
+
+    /// Like [SmartsPattern::matches], but each match only contains the tagged
+    /// atoms, in the order of their map numbers. If the pattern has no tagged
+    /// atoms, this is the same as [SmartsPattern::matches].
+    pub fn tagged_matches(&self, mol: &ROMol) -> Vec<Vec<usize>> {
+        self.tag(self.matches(mol))
+    }
+
+    /// Like [SmartsPattern::tagged_matches], but built from
+    /// [SmartsPattern::all_matches].
+    pub fn all_tagged_matches(&self, mol: &ROMol) -> Vec<Vec<usize>> {
+        self.tag(self.all_matches(mol))
+    }
+
+    /// Reduce each of `matches` to its tagged atoms.
+    fn tag(&self, matches: Vec<Vec<usize>>) -> Vec<Vec<usize>> {
+        if self.tagged.is_empty() {
+            return matches;
+        }
+        matches
+            .into_iter()
+            .map(|m| self.tagged.iter().map(|&t| m[t]).collect())
+            .collect()
+    }
+
+    /// Reports whether `self` matches anywhere in `mol`.
+    pub fn is_match(&self, mol: &ROMol) -> bool {
+        !self.matches(mol).is_empty()
+    }
+}
+
+impl FromStr for SmartsPattern {
+    type Err = RDError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl Display for SmartsPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.smarts)
+    }
+}
+
+impl std::fmt::Debug for SmartsPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SmartsPattern").field(&self.smarts).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::LazyLock;
+
+    use super::*;
+
+    static HYDROXYL: LazyLock<SmartsPattern> =
+        LazyLock::new(|| SmartsPattern::new("[#6:1]-[#8:2]-[#1:3]").unwrap());
+
+    #[test]
+    fn reuse_pattern() {
+        let mut ethanol = ROMol::from_smiles("CCO");
+        ethanol.openff_clean();
+        let mut propane = ROMol::from_smiles("CCC");
+        propane.openff_clean();
+
+        assert_eq!(HYDROXYL.matches(&ethanol), vec![vec![1, 2, 8]]);
+        assert!(!HYDROXYL.is_match(&propane));
+    }
+
+    #[test]
+    fn tagged_order() {
+        let mut ethanol = ROMol::from_smiles("CCO");
+        ethanol.openff_clean();
+        let pat = SmartsPattern::new("[#1:2]-[#8:1]").unwrap();
+        assert_eq!(pat.num_tagged(), 2);
+        assert_eq!(pat.tagged_matches(&ethanol), vec![vec![2, 8]]);
+    }
+
+    #[test]
+    fn empty_pattern() {
+        let pat = SmartsPattern::new("").unwrap();
+        let mol = ROMol::from_smiles("CCO");
+        assert!(pat.matches(&mol).is_empty());
+    }
+
+    #[test]
+    fn bad_smarts() {
+        assert!(SmartsPattern::new("[#6:1]-[").is_err());
+    }
+}
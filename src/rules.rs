@@ -0,0 +1,364 @@
+//! Rule-based property filters like Lipinski's rule of five.
+//!
+//! A [RuleSet] is a named collection of [Rule]s, each bounding one
+//! [Property] of a molecule, along with the number of rules a molecule may
+//! violate and still pass. Several rule sets can be combined into a
+//! [FilterPipeline], which computes each property only once per molecule:
+//!
+//! ```text
+//! let pipeline = FilterPipeline::new(vec![RuleSet::lipinski(), RuleSet::veber()]);
+//! let passing: Vec<_> = mols.iter().filter(|m| pipeline.passes(m)).collect();
+//! ```
+
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{descriptors, torsions::RotatableBonds, ROMol};
+
+/// A molecular property that can be bounded by a [Rule].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Property {
+    /// average molecular weight, see [descriptors::mol_wt]
+    MolWt,
+    /// Crippen LogP, see [descriptors::mol_log_p]
+    LogP,
+    /// Crippen molar refractivity, see [descriptors::mol_mr]
+    MolarRefractivity,
+    /// see [descriptors::num_h_donors]
+    HBondDonors,
+    /// see [descriptors::num_h_acceptors]
+    HBondAcceptors,
+    /// see [descriptors::num_lipinski_hbd]
+    LipinskiHBondDonors,
+    /// see [descriptors::num_lipinski_hba]
+    LipinskiHBondAcceptors,
+    /// strict rotatable bonds, see [descriptors::num_rotatable_bonds]
+    RotatableBonds,
+    /// see [descriptors::tpsa]
+    Tpsa,
+    /// see [descriptors::heavy_atom_count]
+    HeavyAtoms,
+    /// the number of atoms including all hydrogens
+    Atoms,
+    /// see [descriptors::formal_charge]
+    FormalCharge,
+}
+
+impl Property {
+    pub fn compute(&self, mol: &ROMol) -> f64 {
+        match self {
+            Property::MolWt => descriptors::mol_wt(mol),
+            Property::LogP => descriptors::mol_log_p(mol),
+            Property::MolarRefractivity => descriptors::mol_mr(mol),
+            Property::HBondDonors => descriptors::num_h_donors(mol) as f64,
+            Property::HBondAcceptors => {
+                descriptors::num_h_acceptors(mol) as f64
+            }
+            Property::LipinskiHBondDonors => {
+                descriptors::num_lipinski_hbd(mol) as f64
+            }
+            Property::LipinskiHBondAcceptors => {
+                descriptors::num_lipinski_hba(mol) as f64
+            }
+            Property::RotatableBonds => {
+                descriptors::num_rotatable_bonds(mol, RotatableBonds::Strict)
+                    as f64
+            }
+            Property::Tpsa => descriptors::tpsa(mol),
+            Property::HeavyAtoms => descriptors::heavy_atom_count(mol) as f64,
+            Property::Atoms => {
+                let mut mol = mol.clone();
+                mol.add_hs();
+                mol.num_atoms() as f64
+            }
+            Property::FormalCharge => descriptors::formal_charge(mol) as f64,
+        }
+    }
+}
+
+/// A requirement that a [Property] falls within the inclusive range
+/// `min..=max`. Either bound may be infinite.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rule {
+    pub property: Property,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Rule {
+    pub fn new(property: Property, min: f64, max: f64) -> Self {
+        Self { property, min, max }
+    }
+
+    /// A rule with only an upper bound.
+    pub fn at_most(property: Property, max: f64) -> Self {
+        Self::new(property, f64::NEG_INFINITY, max)
+    }
+
+    pub fn check(&self, value: f64) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.min.is_finite(), self.max.is_finite()) {
+            (true, true) => {
+                write!(f, "{} <= {:?} <= {}", self.min, self.property, self.max)
+            }
+            (true, false) => write!(f, "{:?} >= {}", self.property, self.min),
+            (false, true) => write!(f, "{:?} <= {}", self.property, self.max),
+            (false, false) => write!(f, "any {:?}", self.property),
+        }
+    }
+}
+
+/// The outcome of checking a single [Rule].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RuleResult {
+    pub rule: Rule,
+    pub value: f64,
+    pub passed: bool,
+}
+
+/// The outcome of checking every rule in a [RuleSet].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleSetReport {
+    pub name: String,
+    pub results: Vec<RuleResult>,
+    /// whether the number of failed rules is within the allowed violations
+    pub passed: bool,
+}
+
+impl RuleSetReport {
+    /// The rules that were not satisfied.
+    pub fn violations(&self) -> impl Iterator<Item = &RuleResult> {
+        self.results.iter().filter(|r| !r.passed)
+    }
+}
+
+/// A named collection of [Rule]s.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleSet {
+    pub name: String,
+    pub rules: Vec<Rule>,
+    /// the number of rules that may fail while still passing the set
+    pub max_violations: usize,
+}
+
+impl RuleSet {
+    pub fn new(
+        name: impl Into<String>,
+        rules: Vec<Rule>,
+        max_violations: usize,
+    ) -> Self {
+        Self { name: name.into(), rules, max_violations }
+    }
+
+    /// Lipinski's rule of five, allowing one violation. Hydrogen bond donors
+    /// and acceptors are counted by Lipinski's original N/O definitions, as
+    /// in RDKit's `Lipinski` module.
+    pub fn lipinski() -> Self {
+        use Property::*;
+        Self::new(
+            "Lipinski",
+            vec![
+                Rule::at_most(MolWt, 500.0),
+                Rule::at_most(LogP, 5.0),
+                Rule::at_most(LipinskiHBondDonors, 5.0),
+                Rule::at_most(LipinskiHBondAcceptors, 10.0),
+            ],
+            1,
+        )
+    }
+
+    /// Veber's rules for oral bioavailability.
+    pub fn veber() -> Self {
+        use Property::*;
+        Self::new(
+            "Veber",
+            vec![
+                Rule::at_most(RotatableBonds, 10.0),
+                Rule::at_most(Tpsa, 140.0),
+            ],
+            0,
+        )
+    }
+
+    /// Ghose's drug-likeness ranges.
+    pub fn ghose() -> Self {
+        use Property::*;
+        Self::new(
+            "Ghose",
+            vec![
+                Rule::new(MolWt, 160.0, 480.0),
+                Rule::new(LogP, -0.4, 5.6),
+                Rule::new(MolarRefractivity, 40.0, 130.0),
+                Rule::new(Atoms, 20.0, 70.0),
+            ],
+            0,
+        )
+    }
+
+    /// The rule of three for fragment libraries.
+    pub fn rule_of_three() -> Self {
+        use Property::*;
+        Self::new(
+            "Rule of 3",
+            vec![
+                Rule::at_most(MolWt, 300.0),
+                Rule::at_most(LogP, 3.0),
+                Rule::at_most(HBondDonors, 3.0),
+                Rule::at_most(HBondAcceptors, 3.0),
+                Rule::at_most(RotatableBonds, 3.0),
+            ],
+            0,
+        )
+    }
+
+    /// The property ranges of Walters' Rapid Elimination Of Swill.
+    pub fn reos() -> Self {
+        use Property::*;
+        Self::new(
+            "REOS",
+            vec![
+                Rule::new(MolWt, 200.0, 500.0),
+                Rule::new(LogP, -5.0, 5.0),
+                Rule::new(HBondDonors, 0.0, 5.0),
+                Rule::new(HBondAcceptors, 0.0, 10.0),
+                Rule::new(FormalCharge, -2.0, 2.0),
+                Rule::new(RotatableBonds, 0.0, 8.0),
+                Rule::new(HeavyAtoms, 15.0, 50.0),
+            ],
+            0,
+        )
+    }
+
+    fn evaluate_with(
+        &self,
+        mut value: impl FnMut(Property) -> f64,
+    ) -> RuleSetReport {
+        let results: Vec<_> = self
+            .rules
+            .iter()
+            .map(|&rule| {
+                let value = value(rule.property);
+                RuleResult { rule, value, passed: rule.check(value) }
+            })
+            .collect();
+        let violations = results.iter().filter(|r| !r.passed).count();
+        RuleSetReport {
+            name: self.name.clone(),
+            results,
+            passed: violations <= self.max_violations,
+        }
+    }
+
+    /// Check every rule against `mol`.
+    pub fn evaluate(&self, mol: &ROMol) -> RuleSetReport {
+        self.evaluate_with(|p| p.compute(mol))
+    }
+
+    pub fn passes(&self, mol: &ROMol) -> bool {
+        self.evaluate(mol).passed
+    }
+}
+
+/// A sequence of [RuleSet]s applied together, sharing property calculations.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FilterPipeline {
+    sets: Vec<RuleSet>,
+}
+
+impl FilterPipeline {
+    pub fn new(sets: Vec<RuleSet>) -> Self {
+        Self { sets }
+    }
+
+    /// Add `set` to the end of the pipeline.
+    pub fn push(&mut self, set: RuleSet) {
+        self.sets.push(set);
+    }
+
+    pub fn rule_sets(&self) -> &[RuleSet] {
+        &self.sets
+    }
+
+    fn evaluate_with(
+        &self,
+        mut value: impl FnMut(Property) -> f64,
+    ) -> Vec<RuleSetReport> {
+        let mut cache = HashMap::new();
+        self.sets
+            .iter()
+            .map(|set| {
+                set.evaluate_with(|p| {
+                    *cache.entry(p).or_insert_with(|| value(p))
+                })
+            })
+            .collect()
+    }
+
+    /// Evaluate every rule set against `mol`, in order.
+    pub fn evaluate(&self, mol: &ROMol) -> Vec<RuleSetReport> {
+        self.evaluate_with(|p| p.compute(mol))
+    }
+
+    /// Reports whether `mol` passes every rule set in the pipeline.
+    pub fn passes(&self, mol: &ROMol) -> bool {
+        self.evaluate(mol).iter().all(|r| r.passed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allowed_violations() {
+        let set = RuleSet::lipinski();
+        let report = set.evaluate_with(|p| match p {
+            Property::MolWt => 550.0,
+            _ => 1.0,
+        });
+        assert!(report.passed);
+        assert_eq!(report.violations().count(), 1);
+
+        let report = set.evaluate_with(|p| match p {
+            Property::MolWt => 550.0,
+            Property::LogP => 6.0,
+            _ => 1.0,
+        });
+        assert!(!report.passed);
+    }
+
+    #[test]
+    fn shared_properties() {
+        let pipeline =
+            FilterPipeline::new(vec![RuleSet::lipinski(), RuleSet::reos()]);
+        let mut calls = Vec::new();
+        let reports = pipeline.evaluate_with(|p| {
+            calls.push(p);
+            300.0
+        });
+        assert_eq!(reports.len(), 2);
+        // REOS needs five properties that Lipinski doesn't
+        assert_eq!(calls.len(), 9);
+    }
+
+    #[test]
+    fn display() {
+        let rule = Rule::new(Property::LogP, -0.4, 5.6);
+        assert_eq!(rule.to_string(), "-0.4 <= LogP <= 5.6");
+        let rule = Rule::at_most(Property::MolWt, 500.0);
+        assert_eq!(rule.to_string(), "MolWt <= 500");
+    }
+
+    #[test]
+    fn aspirin() {
+        let mol = ROMol::from_smiles("CC(=O)Oc1ccccc1C(=O)O");
+        assert!(RuleSet::lipinski().passes(&mol));
+        assert!(RuleSet::veber().passes(&mol));
+        // aspirin has only 13 heavy atoms
+        assert!(!RuleSet::reos().passes(&mol));
+    }
+}
@@ -0,0 +1,202 @@
+//! [serde] support, enabled with the `serde` feature.
+//!
+//! [BitVector] is serialized compactly as its number of bits and base64
+//! encoding (see [BitVector::to_base64]). [ROMol] is serialized as its RDKit
+//! JSON (see [ROMol::to_json]) in human-readable formats and as a pickle with
+//! all of its properties (see [ROMol::to_binary_with]) in binary formats.
+//!
+//! [Point3D] is defined in `rdkit-sys`, so it can't implement the serde traits
+//! directly. Instead, use the [point3d] and [positions] modules with
+//! `#[serde(with = "...")]` in your own types:
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Message {
+//!     mol: ROMol,
+//!     fp: BitVector,
+//!     #[serde(with = "rdkit_rs::serialize::positions")]
+//!     coords: Vec<Point3D>,
+//! }
+//! ```
+//!
+//! [Conformer] serializes as its positions in the same format, so its
+//! coordinates can be read back into a `Vec<Point3D>` and passed to
+//! [ROMol::add_conformer]. Sparse count fingerprints are plain `HashMap`s and
+//! need no extra support.
+
+use serde::{
+    de::{Error, Visitor},
+    ser::Error as _,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+    bitvector::BitVector, pickle::PickleParams, Conformer, Point3D, ROMol,
+};
+
+#[derive(Serialize, Deserialize)]
+struct BitVectorRepr {
+    num_bits: usize,
+    base64: String,
+}
+
+impl Serialize for BitVector {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        BitVectorRepr { num_bits: self.num_bits(), base64: self.to_base64() }
+            .serialize(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for BitVector {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let BitVectorRepr { num_bits, base64 } = BitVectorRepr::deserialize(d)?;
+        BitVector::from_base64(&base64, num_bits).map_err(|_| {
+            D::Error::custom(format!(
+                "invalid base64 for BitVector of {num_bits} bits"
+            ))
+        })
+    }
+}
+
+impl Serialize for ROMol {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.serialize_str(&self.to_json())
+        } else {
+            let data = self
+                .to_binary_with(&PickleParams::all())
+                .map_err(|_| S::Error::custom("failed to pickle molecule"))?;
+            s.serialize_bytes(&data)
+        }
+    }
+}
+
+struct ROMolVisitor;
+
+impl Visitor<'_> for ROMolVisitor {
+    type Value = ROMol;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("an RDKit JSON string or pickle")
+    }
+
+    fn visit_str<E: Error>(self, json: &str) -> Result<ROMol, E> {
+        ROMol::try_from_json(json)
+            .map_err(|_| E::custom("failed to parse RDKit JSON"))
+    }
+
+    fn visit_bytes<E: Error>(self, data: &[u8]) -> Result<ROMol, E> {
+        ROMol::from_binary(data)
+            .map_err(|_| E::custom("failed to unpickle molecule"))
+    }
+}
+
+impl<'de> Deserialize<'de> for ROMol {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        if d.is_human_readable() {
+            d.deserialize_str(ROMolVisitor)
+        } else {
+            d.deserialize_bytes(ROMolVisitor)
+        }
+    }
+}
+
+impl Serialize for Conformer<'_> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        positions::serialize(&self.get_positions(), s)
+    }
+}
+
+/// Serialize a [Point3D] as an `[x, y, z]` array.
+pub mod point3d {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        p: &Point3D,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        [p.x, p.y, p.z].serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<Point3D, D::Error> {
+        let [x, y, z] = <[f64; 3]>::deserialize(d)?;
+        Ok(Point3D { x, y, z })
+    }
+}
+
+/// Serialize a sequence of [Point3D]s as a sequence of `[x, y, z]` arrays.
+pub mod positions {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        ps: &[Point3D],
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        s.collect_seq(ps.iter().map(|p| [p.x, p.y, p.z]))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<Vec<Point3D>, D::Error> {
+        let ps = Vec::<[f64; 3]>::deserialize(d)?;
+        Ok(ps
+            .into_iter()
+            .map(|[x, y, z]| Point3D { x, y, z })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Coords {
+        #[serde(with = "point3d")]
+        center: Point3D,
+        #[serde(with = "positions")]
+        atoms: Vec<Point3D>,
+    }
+
+    #[test]
+    fn bit_vector() {
+        let mut fp = BitVector::zeros(70);
+        fp.set(1);
+        fp.set(69);
+        let json = serde_json::to_string(&fp).unwrap();
+        assert_eq!(json, r#"{"num_bits":70,"base64":"AgAAAAAAAAAg"}"#);
+        let got: BitVector = serde_json::from_str(&json).unwrap();
+        assert_eq!(got, fp);
+
+        let bad = r#"{"num_bits":8,"base64":"AgAAAAAAAAAg"}"#;
+        assert!(serde_json::from_str::<BitVector>(bad).is_err());
+    }
+
+    #[test]
+    fn points() {
+        let coords = Coords {
+            center: Point3D { x: 0.0, y: 1.0, z: 2.0 },
+            atoms: vec![Point3D { x: 1.5, y: 0.0, z: -1.0 }],
+        };
+        let json = serde_json::to_string(&coords).unwrap();
+        assert_eq!(
+            json,
+            r#"{"center":[0.0,1.0,2.0],"atoms":[[1.5,0.0,-1.0]]}"#
+        );
+        let got: Coords = serde_json::from_str(&json).unwrap();
+        assert_eq!(got.center.y, 1.0);
+        assert_eq!(got.atoms[0].z, -1.0);
+    }
+
+    #[test]
+    fn mol() {
+        let mol = ROMol::from_smiles("CC(=O)O");
+        let json = serde_json::to_string(&mol).unwrap();
+        let got: ROMol = serde_json::from_str(&json).unwrap();
+        assert_eq!(got.to_smiles(), mol.to_smiles());
+
+        assert!(serde_json::from_str::<ROMol>(r#""not a molecule""#).is_err());
+    }
+}
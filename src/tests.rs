@@ -2,6 +2,25 @@ use std::fs::read_to_string;
 
 use super::*;
 
+/// A bit vector of `n` bits with only `bits` set.
+pub(crate) fn bv(bits: &[usize], n: usize) -> BitVector {
+    let mut ret = BitVector::zeros(n);
+    for &b in bits {
+        ret.set(b);
+    }
+    ret
+}
+
+/// `smiles` with explicit hydrogens and `num_confs` conformers embedded with
+/// a fixed random seed.
+pub(crate) fn embedded(smiles: &str, num_confs: usize) -> ROMol {
+    let mut mol = ROMol::from_smiles(smiles);
+    mol.add_hs();
+    let params = EmbedParameters { random_seed: 42, ..Default::default() };
+    mol.embed_multiple_confs(num_confs, &params);
+    mol
+}
+
 #[test]
 fn to_inchi_key() {
     let benzene = ROMol::from_smiles("C1=CC=CC=C1");
@@ -32,7 +51,63 @@ fn elements() {
 
 #[test]
 fn get_2d_coords() {
-    let mol = ROMol::from_smiles("CCO");
+    let mut mol = ROMol::from_smiles("CCO");
     let coords = mol.get_2d_coords();
     assert_eq!(coords.len(), 3);
 }
+
+#[test]
+fn canonical_ranks() {
+    let mol = ROMol::from_smiles("OCC(C)C");
+    let mut got = mol.canonical_ranks(true);
+    got.sort();
+    assert_eq!(got, [0, 1, 2, 3, 4]);
+
+    let ranks = mol.canonical_ranks(false);
+    assert_eq!(ranks[3], ranks[4]);
+    assert_ne!(ranks[0], ranks[1]);
+}
+
+#[test]
+fn equivalent_atoms() {
+    let mol = ROMol::from_smiles("OCC(C)C");
+    let got = mol.equivalent_atoms();
+    let want = vec![vec![0], vec![1], vec![2], vec![3, 4]];
+    assert_eq!(got, want);
+}
+
+#[test]
+fn romol_eq() {
+    let a = ROMol::from_smiles("OCC");
+    let b = ROMol::from_smiles("CCO");
+    let c = ROMol::from_smiles("CCC");
+    assert!(a == b);
+    assert!(a != c);
+}
+
+#[test]
+fn conformers() {
+    let mut mol = ROMol::from_smiles("CO");
+    assert_eq!(mol.num_conformers(), 0);
+
+    let pos = [
+        Point3D { x: 0.0, y: 0.0, z: 0.0 },
+        Point3D { x: 1.4, y: 0.0, z: 0.0 },
+    ];
+    let a = mol.add_conformer(&pos);
+    let b = mol.add_conformer(&pos);
+    assert_eq!(mol.conformer_ids(), [a, b]);
+    assert!(mol.get_conformer(a).is_3d());
+
+    mol.get_conformer_mut(b)
+        .set_atom_position(1, Point3D { x: 0.0, y: 1.4, z: 0.5 });
+    let got = mol.get_conformer(b).get_atom_position(1);
+    assert_eq!((got.x, got.y, got.z), (0.0, 1.4, 0.5));
+    let got = mol.get_conformer(a).get_atom_position(1);
+    assert_eq!((got.x, got.y, got.z), (1.4, 0.0, 0.0));
+
+    mol.remove_conformer(a);
+    assert_eq!(mol.conformer_ids(), [b]);
+    mol.clear_conformers();
+    assert_eq!(mol.num_conformers(), 0);
+}
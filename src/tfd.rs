@@ -0,0 +1,311 @@
+//! Torsion Fingerprint Deviation (TFD) between conformers.
+//!
+//! This is modeled on RDKit's Python `TorsionFingerprints` module, which
+//! implements the method of Schulz-Gasch et al. (J. Chem. Inf. Model. 2012, 52,
+//! 1499). Each rotatable bond between two heavy atoms, as found by
+//! [ROMol::rotatable_bonds] with the [RotatableBonds::NonStrict] definition,
+//! contributes one torsion. The deviation of a torsion between two conformers
+//! is the absolute difference in its dihedral angle, normalized by 180°, and
+//! the TFD is the weighted average of these deviations over all torsions.
+//!
+//! Only the non-ring torsions are considered, and every torsion uses the
+//! maximal deviation of 180°, corresponding to the `maxDev='equal'` option in
+//! RDKit.
+
+use std::collections::VecDeque;
+
+use crate::{
+    torsions::{RotatableBonds, TorsionParams},
+    ROMol,
+};
+
+/// The bonds contributing torsions: every [RotatableBonds::NonStrict] bond,
+/// including amides and bonds to methyl groups. Terminal atoms have no
+/// reference neighbors, so their torsions are skipped.
+const TFD_BONDS: TorsionParams = TorsionParams {
+    definition: RotatableBonds::NonStrict,
+    terminal_methyls: true,
+    amides: true,
+    rings: false,
+};
+
+/// A torsion around the bond between the middle two atoms of its quadruples.
+/// When the neighbors on either side are symmetry-equivalent, there is one
+/// quadruple for each combination of them, and the smallest deviation among
+/// the combinations is used.
+#[derive(Debug, PartialEq)]
+struct Torsion {
+    quads: Vec<[usize; 4]>,
+}
+
+impl Torsion {
+    fn central(&self) -> (usize, usize) {
+        let [_, j, k, _] = self.quads[0];
+        (j, k)
+    }
+}
+
+/// Choose the reference neighbors of `atom` for a torsion about the
+/// `atom`-`partner` bond.
+fn reference_atoms(
+    atom: usize,
+    partner: usize,
+    neighbors: &[Vec<usize>],
+    classes: &[usize],
+) -> Vec<usize> {
+    let cands: Vec<_> = neighbors[atom]
+        .iter()
+        .copied()
+        .filter(|&n| n != partner)
+        .collect();
+    if cands.len() <= 1
+        || cands.iter().all(|&n| classes[n] == classes[cands[0]])
+    {
+        return cands;
+    }
+    let unique = cands.iter().copied().filter(|&n| {
+        cands.iter().filter(|&&m| classes[m] == classes[n]).count() == 1
+    });
+    let best = unique
+        .min_by_key(|&n| classes[n])
+        .or_else(|| cands.iter().copied().min_by_key(|&n| classes[n]));
+    best.into_iter().collect()
+}
+
+/// The heavy-atom adjacency list of `mol`.
+fn heavy_neighbors(mol: &ROMol) -> Vec<Vec<usize>> {
+    let elements = mol.elements();
+    let mut ret = vec![Vec::new(); elements.len()];
+    for bond in mol.bonds() {
+        if elements[bond.begin] != 1 && elements[bond.end] != 1 {
+            ret[bond.begin].push(bond.end);
+            ret[bond.end].push(bond.begin);
+        }
+    }
+    ret
+}
+
+fn torsion_list(mol: &ROMol, neighbors: &[Vec<usize>]) -> Vec<Torsion> {
+    let classes = mol.symmetry_classes();
+    mol.rotatable_bonds(&TFD_BONDS)
+        .into_iter()
+        .filter_map(|(j, k)| {
+            let is = reference_atoms(j, k, neighbors, &classes);
+            let ls = reference_atoms(k, j, neighbors, &classes);
+            if is.is_empty() || ls.is_empty() {
+                return None;
+            }
+            let quads = is
+                .iter()
+                .flat_map(|&i| ls.iter().map(move |&l| [i, j, k, l]))
+                .collect();
+            Some(Torsion { quads })
+        })
+        .collect()
+}
+
+/// The number of bonds on the shortest path between every pair of atoms, or
+/// `usize::MAX` if there is no path.
+fn topological_distances(neighbors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let n = neighbors.len();
+    let mut ret = vec![vec![usize::MAX; n]; n];
+    for (start, dists) in ret.iter_mut().enumerate() {
+        dists[start] = 0;
+        let mut queue = VecDeque::from([start]);
+        while let Some(cur) = queue.pop_front() {
+            for &next in &neighbors[cur] {
+                if dists[next] == usize::MAX {
+                    dists[next] = dists[cur] + 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    ret
+}
+
+/// Weight each torsion by its distance from the central bond of the molecule,
+/// decaying from 1 for the central bond itself to 0.1 for the most distant
+/// torsion.
+fn torsion_weights(torsions: &[Torsion], neighbors: &[Vec<usize>]) -> Vec<f64> {
+    let dist = topological_distances(neighbors);
+    let heavy: Vec<_> = (0..neighbors.len())
+        .filter(|&a| !neighbors[a].is_empty())
+        .collect();
+    let bond_dist =
+        |(a, b): (usize, usize), x: usize| dist[a][x].min(dist[b][x]);
+
+    // the central bond is the one closest to every other heavy atom
+    let Some(center) = heavy
+        .iter()
+        .flat_map(|&a| neighbors[a].iter().map(move |&b| (a, b)))
+        .filter(|&(a, b)| a < b)
+        .min_by_key(|&bond| heavy.iter().map(|&x| bond_dist(bond, x)).max())
+    else {
+        return vec![1.0; torsions.len()];
+    };
+
+    let ds: Vec<_> = torsions
+        .iter()
+        .map(|t| {
+            let (j, k) = t.central();
+            if (j.min(k), j.max(k)) == center {
+                0
+            } else {
+                bond_dist(center, j)
+                    .min(bond_dist(center, k))
+                    .saturating_add(1)
+            }
+        })
+        .collect();
+    let dmax = ds.iter().copied().max().unwrap_or(0);
+    if dmax == 0 {
+        return vec![1.0; torsions.len()];
+    }
+    ds.into_iter()
+        .map(|d| 0.1f64.powf(d as f64 / dmax as f64))
+        .collect()
+}
+
+/// The absolute difference between two angles in degrees, in [0, 180].
+fn angle_deviation(a: f64, b: f64) -> f64 {
+    let d = (a - b).abs() % 360.0;
+    d.min(360.0 - d)
+}
+
+/// Compute the TFD from the dihedral angles of each torsion in two conformers
+/// and the weight of each torsion.
+fn tfd(angles1: &[Vec<f64>], angles2: &[Vec<f64>], weights: &[f64]) -> f64 {
+    let mut num = 0.0;
+    let mut den = 0.0;
+    for ((a1, a2), w) in angles1.iter().zip(angles2).zip(weights) {
+        let dev = a1
+            .iter()
+            .flat_map(|&a| a2.iter().map(move |&b| angle_deviation(a, b)))
+            .fold(f64::INFINITY, f64::min);
+        num += w * dev / 180.0;
+        den += w;
+    }
+    if den == 0.0 {
+        return 0.0;
+    }
+    num / den
+}
+
+impl ROMol {
+    fn torsion_angles(
+        &self,
+        conf: usize,
+        torsions: &[Torsion],
+    ) -> Vec<Vec<f64>> {
+        let conf = self.get_conformer(conf);
+        torsions
+            .iter()
+            .map(|t| {
+                t.quads
+                    .iter()
+                    .map(|&[i, j, k, l]| conf.get_dihedral_deg(i, j, k, l))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn torsions_and_weights(
+        &self,
+        use_weights: bool,
+    ) -> (Vec<Torsion>, Vec<f64>) {
+        let neighbors = heavy_neighbors(self);
+        let torsions = torsion_list(self, &neighbors);
+        let weights = if use_weights {
+            torsion_weights(&torsions, &neighbors)
+        } else {
+            vec![1.0; torsions.len()]
+        };
+        (torsions, weights)
+    }
+
+    /// The Torsion Fingerprint Deviation between conformers `conf1` and
+    /// `conf2`, ranging from 0 for identical torsions to 1 for every torsion
+    /// differing by 180°. If `use_weights` is true, torsions near the center
+    /// of the molecule count more than those near the edges.
+    pub fn tfd_between_conformers(
+        &self,
+        conf1: usize,
+        conf2: usize,
+        use_weights: bool,
+    ) -> f64 {
+        let (torsions, weights) = self.torsions_and_weights(use_weights);
+        tfd(
+            &self.torsion_angles(conf1, &torsions),
+            &self.torsion_angles(conf2, &torsions),
+            &weights,
+        )
+    }
+
+    /// The symmetric matrix of TFDs between every pair of conformers of
+    /// `self`, in the order of [ROMol::conformer_ids]. See
+    /// [ROMol::tfd_between_conformers].
+    pub fn tfd_matrix(&self, use_weights: bool) -> Vec<Vec<f64>> {
+        let (torsions, weights) = self.torsions_and_weights(use_weights);
+        let angles: Vec<_> = self
+            .conformer_ids()
+            .into_iter()
+            .map(|id| self.torsion_angles(id, &torsions))
+            .collect();
+        let n = angles.len();
+        let mut ret = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            for j in 0..i {
+                let d = tfd(&angles[i], &angles[j], &weights);
+                ret[i][j] = d;
+                ret[j][i] = d;
+            }
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::embedded;
+
+    use super::*;
+
+    #[test]
+    fn deviation() {
+        assert_eq!(angle_deviation(170.0, -170.0), 20.0);
+        assert_eq!(angle_deviation(-60.0, 60.0), 120.0);
+        assert_eq!(angle_deviation(0.0, 180.0), 180.0);
+    }
+
+    #[test]
+    fn weighted_tfd() {
+        let a1 = vec![vec![60.0], vec![180.0, -60.0]];
+        let a2 = vec![vec![-120.0], vec![60.0]];
+        assert_eq!(tfd(&a1, &a2, &[1.0, 1.0]), 0.5 * (1.0 + 120.0 / 180.0));
+        assert_eq!(tfd(&a1, &a2, &[1.0, 0.0]), 1.0);
+        assert_eq!(tfd(&[], &[], &[]), 0.0);
+    }
+
+    #[test]
+    fn distances() {
+        // a chain 0-1-2 and an isolated atom 3
+        let neighbors = vec![vec![1], vec![0, 2], vec![1], vec![]];
+        let got = topological_distances(&neighbors);
+        assert_eq!(got[0], [0, 1, 2, usize::MAX]);
+        assert_eq!(got[2][0], 2);
+    }
+
+    #[test]
+    fn butane_torsions() {
+        let mol = embedded("CCCC", 3);
+        let neighbors = heavy_neighbors(&mol);
+        let torsions = torsion_list(&mol, &neighbors);
+        assert_eq!(torsions, [Torsion { quads: vec![[0, 1, 2, 3]] }]);
+
+        let mat = mol.tfd_matrix(true);
+        assert_eq!(mat.len(), 3);
+        assert!(mat.iter().flatten().all(|&d| (0.0..=1.0).contains(&d)));
+        assert_eq!(mol.tfd_between_conformers(0, 1, true), mat[1][0]);
+    }
+}
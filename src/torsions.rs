@@ -0,0 +1,257 @@
+//! Enumerating rotatable bonds and the torsions around them.
+//!
+//! Rotatable bonds are found with one of RDKit's rotatable bond SMARTS
+//! patterns, selected by [RotatableBonds], and then optionally filtered to
+//! remove terminal methyl groups and amide bonds. Each bond is reported as a
+//! single dihedral quadruple (i, j, k, l) around the central j-k bond, with the
+//! outer atoms chosen by canonical rank so that the same torsion is returned
+//! regardless of the input atom order.
+
+use std::sync::LazyLock;
+
+use crate::{Bond, ROMol, SmartsPattern};
+
+/// RDKit's `NonStrict` definition: any acyclic single bond between two
+/// non-terminal atoms that are not part of a triple bond
+const NON_STRICT: &str = "[!$(*#*)&!D1]-&!@[!$(*#*)&!D1]";
+
+/// RDKit's `Strict` definition, which additionally excludes amides, esters,
+/// and similar conjugated bonds, as well as bonds to symmetric groups like
+/// trihalomethyl and tert-butyl
+const STRICT: &str = "[!$(*#*)&!D1&!$(C(F)(F)F)&!$(C(Cl)(Cl)Cl)\
+&!$(C(Br)(Br)Br)&!$(C([CH3])([CH3])[CH3])&!$([CD3](=[N,O,S])-!@[#7,O,S!D1])\
+&!$([#7,O,S!D1]-!@[CD3]=[N,O,S])&!$([CD3](=[N+])-!@[#7!D1])\
+&!$([#7!D1]-!@[CD3]=[N+])]-,:;!@[!$(*#*)&!D1&!$(C(F)(F)F)\
+&!$(C(Cl)(Cl)Cl)&!$(C(Br)(Br)Br)&!$(C([CH3])([CH3])[CH3])]";
+
+static NON_STRICT_PATTERN: LazyLock<SmartsPattern> =
+    LazyLock::new(|| SmartsPattern::new(NON_STRICT).unwrap());
+
+static STRICT_PATTERN: LazyLock<SmartsPattern> =
+    LazyLock::new(|| SmartsPattern::new(STRICT).unwrap());
+
+static AMIDE_PATTERN: LazyLock<SmartsPattern> =
+    LazyLock::new(|| SmartsPattern::new("[#7:1]-!@[#6:2]=[#8]").unwrap());
+
+/// The definition of a rotatable bond, see [TorsionParams].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RotatableBonds {
+    NonStrict,
+    #[default]
+    Strict,
+}
+
+impl RotatableBonds {
+    /// The SMARTS pattern matching the two atoms of a rotatable bond.
+    pub fn smarts(&self) -> &'static str {
+        match self {
+            RotatableBonds::NonStrict => NON_STRICT,
+            RotatableBonds::Strict => STRICT,
+        }
+    }
+
+    fn pattern(&self) -> &'static SmartsPattern {
+        match self {
+            RotatableBonds::NonStrict => &NON_STRICT_PATTERN,
+            RotatableBonds::Strict => &STRICT_PATTERN,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TorsionParams {
+    /// defaults to [RotatableBonds::Strict]
+    pub definition: RotatableBonds,
+    /// include bonds to methyl groups with explicit hydrogens, which the
+    /// SMARTS definitions only exclude when the hydrogens are implicit.
+    /// defaults to false
+    pub terminal_methyls: bool,
+    /// include amide C-N bonds. these are already excluded by
+    /// [RotatableBonds::Strict]. defaults to false
+    pub amides: bool,
+    /// include torsions around non-aromatic single bonds in rings. defaults
+    /// to false
+    pub rings: bool,
+}
+
+/// Order a torsion so that its central atoms are increasing.
+fn canonical_torsion([i, j, k, l]: [usize; 4]) -> [usize; 4] {
+    if j > k {
+        [l, k, j, i]
+    } else {
+        [i, j, k, l]
+    }
+}
+
+/// Pick the outer atom of a torsion around the `atom`-`partner` bond from the
+/// neighbors of `atom` accepted by `filter`, preferring heavy atoms and then
+/// the lowest canonical rank.
+fn outer_atom(
+    atom: usize,
+    partner: usize,
+    bonds: &[Bond],
+    elements: &[usize],
+    ranks: &[usize],
+    filter: impl Fn(&Bond) -> bool,
+) -> Option<usize> {
+    bonds
+        .iter()
+        .filter(|b| (b.begin == atom || b.end == atom) && filter(b))
+        .map(|b| b.other(atom))
+        .filter(|&n| n != partner)
+        .min_by_key(|&n| (elements[n] == 1, ranks[n]))
+}
+
+/// Reports whether `atom` is the carbon of a methyl group with explicit
+/// hydrogens.
+fn is_methyl(atom: usize, bonds: &[Bond], elements: &[usize]) -> bool {
+    if elements[atom] != 6 {
+        return false;
+    }
+    let (hs, heavy) = bonds
+        .iter()
+        .filter(|b| b.begin == atom || b.end == atom)
+        .map(|b| b.other(atom))
+        .fold((0, 0), |(hs, heavy), n| {
+            if elements[n] == 1 {
+                (hs + 1, heavy)
+            } else {
+                (hs, heavy + 1)
+            }
+        });
+    hs == 3 && heavy == 1
+}
+
+impl ROMol {
+    /// Returns the rotatable bonds in `self` according to `params`, as pairs
+    /// of atom indices with the lower index first, sorted. Ring bonds are
+    /// never rotatable, so [TorsionParams::rings] is ignored.
+    pub fn rotatable_bonds(
+        &self,
+        params: &TorsionParams,
+    ) -> Vec<(usize, usize)> {
+        let elements = self.elements();
+        let bonds = self.bonds();
+        let amides: Vec<_> = if params.amides {
+            Vec::new()
+        } else {
+            AMIDE_PATTERN
+                .tagged_matches(self)
+                .into_iter()
+                .map(|m| (m[0].min(m[1]), m[0].max(m[1])))
+                .collect()
+        };
+        let mut ret: Vec<_> = params
+            .definition
+            .pattern()
+            .matches(self)
+            .into_iter()
+            .map(|m| (m[0].min(m[1]), m[0].max(m[1])))
+            .filter(|bond| !amides.contains(bond))
+            .filter(|&(j, k)| {
+                params.terminal_methyls
+                    || !(is_methyl(j, &bonds, &elements)
+                        || is_methyl(k, &bonds, &elements))
+            })
+            .collect();
+        ret.sort();
+        ret.dedup();
+        ret
+    }
+
+    /// Returns one dihedral quadruple (i, j, k, l) for each rotatable bond j-k
+    /// in `self`, along with the ring torsions if [TorsionParams::rings] is
+    /// set. Within each torsion, j < k, and the outer atoms are the heavy
+    /// neighbors with the lowest canonical rank, falling back to hydrogens.
+    /// For ring torsions, the outer atoms are also in the ring.
+    pub fn rotatable_torsions(
+        &self,
+        params: &TorsionParams,
+    ) -> Vec<[usize; 4]> {
+        let elements = self.elements();
+        let bonds = self.bonds();
+        let ranks = self.canonical_ranks(true);
+        let outer = |atom, partner, filter: fn(&Bond) -> bool| {
+            outer_atom(atom, partner, &bonds, &elements, &ranks, filter)
+        };
+
+        let mut ret = Vec::new();
+        for (j, k) in self.rotatable_bonds(params) {
+            if let (Some(i), Some(l)) =
+                (outer(j, k, |_| true), outer(k, j, |_| true))
+            {
+                ret.push(canonical_torsion([i, j, k, l]));
+            }
+        }
+        if params.rings {
+            for bond in bonds.iter().filter(|b| b.in_ring && b.order == 1.0) {
+                let (j, k) = (bond.begin, bond.end);
+                let in_ring = |b: &Bond| b.in_ring;
+                if let (Some(i), Some(l)) =
+                    (outer(j, k, in_ring), outer(k, j, in_ring))
+                {
+                    // skip three-membered rings, where i and l coincide
+                    if i != l {
+                        ret.push(canonical_torsion([i, j, k, l]));
+                    }
+                }
+            }
+        }
+        ret.sort();
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical() {
+        assert_eq!(canonical_torsion([5, 3, 1, 0]), [0, 1, 3, 5]);
+        assert_eq!(canonical_torsion([5, 1, 3, 0]), [5, 1, 3, 0]);
+    }
+
+    #[test]
+    fn butane() {
+        let mut mol = ROMol::from_smiles("CCCC");
+        mol.openff_clean();
+        let params = TorsionParams::default();
+        assert_eq!(mol.rotatable_bonds(&params), [(1, 2)]);
+        assert_eq!(mol.rotatable_torsions(&params), [[0, 1, 2, 3]]);
+
+        let params = TorsionParams { terminal_methyls: true, ..params };
+        assert_eq!(mol.rotatable_bonds(&params), [(0, 1), (1, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn amides() {
+        let mut mol = ROMol::from_smiles("CC(=O)NCC");
+        mol.openff_clean();
+        let strict = TorsionParams::default();
+        assert_eq!(mol.rotatable_bonds(&strict), [(3, 4)]);
+
+        let non_strict = TorsionParams {
+            definition: RotatableBonds::NonStrict,
+            ..Default::default()
+        };
+        assert_eq!(mol.rotatable_bonds(&non_strict), [(3, 4)]);
+
+        let with_amides = TorsionParams { amides: true, ..non_strict };
+        assert_eq!(mol.rotatable_bonds(&with_amides), [(1, 3), (3, 4)]);
+    }
+
+    #[test]
+    fn ring_torsions() {
+        let mut mol = ROMol::from_smiles("C1CCCCC1");
+        mol.openff_clean();
+        let params = TorsionParams { rings: true, ..Default::default() };
+        let got = mol.rotatable_torsions(&params);
+        assert_eq!(got.len(), 6);
+        assert!(got.iter().flatten().all(|&a| a < 6));
+
+        let mut mol = ROMol::from_smiles("C1CC1");
+        mol.openff_clean();
+        assert!(mol.rotatable_torsions(&params).is_empty());
+    }
+}